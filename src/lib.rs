@@ -1,4 +1,4 @@
-use std::{collections::BinaryHeap, cmp::Ordering};
+use std::{cell::RefCell, collections::{BinaryHeap, VecDeque}, cmp::Ordering, iter::{Fuse, FusedIterator, Peekable}, rc::Rc, sync::{atomic::AtomicUsize, Arc, Mutex}};
 
 /// Sequential iterator iterates over 1 or more iterators
 /// It consumes in the order of adding. After one exhausted, it 
@@ -22,46 +22,805 @@ use std::{collections::BinaryHeap, cmp::Ordering};
 /// }
 /// 
 /// ```
-pub struct SeqIter<T> {
+///
+/// Sub-iterators may borrow, the SeqIter then can't outlive the borrowed data.
+///
+/// ```
+/// use iterutils::SeqIter;
+/// let words = vec![String::from("a"), String::from("b")];
+/// let mut seq_iter = SeqIter::new();
+/// seq_iter.add(Box::new(words.iter()));
+/// seq_iter.add(Box::new(words.iter().rev()));
+/// assert_eq!(seq_iter.map(|x| x.as_str()).collect::<String>(), "abba");
+/// ```
+///
+/// SeqIter is neither `Send` nor `Sync`. The sub-iterators and the callback are
+/// boxed without a `Send` bound, so they may hold an `Rc` or other thread bound
+/// state, and implementing `Send` anyway would be unsound.
+/// Create the SeqIter on the thread that consumes it.
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<iterutils::SeqIter<i32>>();
+/// ```
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<iterutils::SeqIter<i32>>();
+/// ```
+pub struct SeqIter<'a, T> {
     ptr: usize,
-    iters: Vec<Box<dyn Iterator<Item = T>>>,
+    iters: Vec<Box<dyn Iterator<Item = T> + 'a>>,
+    on_exhausted: Option<Box<dyn Fn(usize) + 'a>>,
+    // Set by the first None, so iterators added afterwards are never consumed
+    finished: bool,
 }
 
-impl<T> SeqIter<T> {
+impl<'a, T> SeqIter<'a, T> {
     // Create an empty SeqIter.
-    pub fn new() -> SeqIter<T> {
-        return SeqIter { ptr: 0, iters: Vec::new() }
+    pub fn new() -> SeqIter<'a, T> {
+        SeqIter { ptr: 0, iters: Vec::new(), on_exhausted: None, finished: false }
     }
 
-    // Add more Boxed iterator into the sequential iterator
-    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T>>) {
+    /// Create an empty SeqIter with room for `n` sub-iterators.
+    ///
+    /// The capacity is only a hint to avoid reallocation while adding; more
+    /// than `n` iterators can still be added.
+    pub fn with_capacity(n: usize) -> SeqIter<'a, T> {
+        SeqIter { ptr: 0, iters: Vec::with_capacity(n), on_exhausted: None, finished: false }
+    }
+
+    /// Create a SeqIter consuming the given iterators in order.
+    /// The vector is used as is, without copying.
+    pub fn from_vec(iters: Vec<Box<dyn Iterator<Item=T> + 'a>>) -> SeqIter<'a, T> {
+        SeqIter { ptr: 0, iters, on_exhausted: None, finished: false }
+    }
+
+    /// Add more Boxed iterator into the sequential iterator
+    ///
+    /// It may be called after iteration has started: the iterator goes after
+    /// all others and is reached in turn. Once `next` returned `None` the
+    /// SeqIter is fused and iterators added afterwards are silently never
+    /// consumed. Earlier versions resumed iteration with them instead.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let mut seq_iter = SeqIter::from_vec(vec![Box::new(vec![1].into_iter())]);
+    /// assert_eq!(seq_iter.next(), Some(1));
+    /// seq_iter.add(Box::new(vec![2].into_iter()));
+    /// assert_eq!(seq_iter.next(), Some(2));
+    /// assert_eq!(seq_iter.next(), None);
+    /// seq_iter.add(Box::new(vec![3].into_iter()));
+    /// assert_eq!(seq_iter.next(), None);
+    /// ```
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>) {
         self.iters.push(iter);
     }
 
-    fn get_current(&mut self) -> Option<&mut Box<dyn Iterator<Item = T>>> {
-        return self.iters.get_mut(self.ptr);
+    /// Add several Boxed iterators, same as calling `add` for each in order.
+    pub fn add_many(&mut self, iters: impl IntoIterator<Item=Box<dyn Iterator<Item=T> + 'a>>) {
+        self.iters.extend(iters);
+    }
+
+    /// Add a Boxed iterator in front of all iterators not yet exhausted, so it
+    /// is consumed next.
+    ///
+    /// Exhausted iterators keep their index; the new iterator takes the
+    /// `current_index()` slot and everything after it shifts by one.
+    pub fn add_front(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>) {
+        let pos = self.current_index();
+        self.iters.insert(pos, iter);
+    }
+
+    /// Insert a Boxed iterator at position `pos`, shifting all iterators after it.
+    ///
+    /// The current iterator stays current. Inserting at or before
+    /// `current_index()` places the iterator among the exhausted ones, so it
+    /// will never be consumed; use `add_front` to consume it next.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos > source_count()`, same as `Vec::insert`.
+    pub fn insert(&mut self, pos: usize, iter: Box<dyn Iterator<Item=T> + 'a>) {
+        self.iters.insert(pos, iter);
+        if pos <= self.ptr {
+            self.ptr += 1;
+        }
+    }
+
+    /// Abandon the current sub-iterator and move on to the next one.
+    ///
+    /// Returns the number of sub-iterators left after skipping, the next one
+    /// included. Does nothing and returns 0 when all sub-iterators are
+    /// exhausted or `next` returned None.
+    pub fn skip_current(&mut self) -> usize {
+        if self.finished {
+            return 0;
+        }
+        if self.ptr < self.iters.len() {
+            self.ptr += 1;
+        }
+        self.iters.len() - self.ptr
+    }
+
+    /// Index of the sub-iterator currently being consumed.
+    ///
+    /// Once every sub-iterator is exhausted this equals `source_count()`.
+    pub fn current_index(&self) -> usize {
+        if self.finished {
+            return self.iters.len();
+        }
+        self.ptr.min(self.iters.len())
+    }
+
+    /// Number of sub-iterators registered, including exhausted ones.
+    pub fn source_count(&self) -> usize {
+        self.iters.len()
+    }
+
+    /// Number of sub-iterators not exhausted yet, the current one included.
+    /// Always 0 once `next` returned `None`.
+    pub fn remaining_source_count(&self) -> usize {
+        if self.finished {
+            return 0;
+        }
+        self.iters.len().saturating_sub(self.ptr)
+    }
+
+    /// True when every registered sub-iterator has been exhausted.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_source_count() == 0
+    }
+
+    /// Register a callback invoked with the index of each sub-iterator as soon
+    /// as it runs dry, e.g. for progress reporting.
+    ///
+    /// Sub-iterators abandoned with `skip_current` are not reported.
+    pub fn on_source_exhausted(mut self, cb: impl Fn(usize) + 'a) -> SeqIter<'a, T> {
+        self.on_exhausted = Some(Box::new(cb));
+        self
+    }
+
+    /// Transform every registered sub-iterator, e.g. to apply a per-source `map`
+    /// before concatenation. Exhausted sub-iterators stay exhausted.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let mut seq_iter = SeqIter::new();
+    /// seq_iter.add(Box::new(vec![1, 2].into_iter()));
+    /// seq_iter.add(Box::new(vec![1, 2].into_iter()));
+    /// let mut offset = 0;
+    /// let shifted = seq_iter.map_sources(|iter| {
+    ///     offset += 10;
+    ///     let o = offset;
+    ///     Box::new(iter.map(move |x| (x + o).to_string()))
+    /// });
+    /// assert_eq!(shifted.collect::<Vec<_>>(), vec!["11", "12", "21", "22"]);
+    /// ```
+    pub fn map_sources<U, F>(self, f: F) -> SeqIter<'a, U>
+        where F: FnMut(Box<dyn Iterator<Item=T> + 'a>) -> Box<dyn Iterator<Item=U> + 'a>
+    {
+        SeqIter {
+            ptr: self.ptr,
+            iters: self.iters.into_iter().map(f).collect(),
+            on_exhausted: self.on_exhausted,
+            finished: self.finished,
+        }
+    }
+
+    /// Limit every registered sub-iterator to its first `n` items.
+    /// Unlike `take`, the limit applies to each sub-iterator separately.
+    /// Iterators added afterwards are not limited.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let seq_iter = SeqIter::from(vec![vec![1, 2, 3], vec![4], vec![5, 6]]);
+    /// assert_eq!(seq_iter.take_n_from_each(2).collect::<Vec<_>>(), vec![1, 2, 4, 5, 6]);
+    /// ```
+    pub fn take_n_from_each(self, n: usize) -> SeqIter<'a, T>
+        where T: 'a
+    {
+        self.map_sources(|iter| Box::new(iter.take(n)))
+    }
+
+    /// Skip the first `n` items of every registered sub-iterator, e.g. headers.
+    /// Iterators added afterwards are not affected.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let seq_iter = SeqIter::from(vec![vec!["header", "a"], vec!["header", "b", "c"]]);
+    /// assert_eq!(seq_iter.skip_n_from_each(1).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    /// ```
+    pub fn skip_n_from_each(self, n: usize) -> SeqIter<'a, T>
+        where T: 'a
+    {
+        self.map_sources(|iter| Box::new(iter.skip(n)))
+    }
+
+    /// Exact number of items left, when every remaining sub-iterator reports
+    /// an exact size hint (e.g. `Vec` or array iterators); otherwise None.
+    ///
+    /// SeqIter can't implement `ExactSizeIterator` because its sub-iterators are
+    /// boxed trait objects whose exactness is only known at runtime.
+    pub fn exact_len(&self) -> Option<usize> {
+        match self.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower),
+            _ => None,
+        }
+    }
+
+    /// Collect the remaining items into a `Vec`, allocating room for the
+    /// size hint's lower bound up front.
+    pub fn collect_vec(self) -> Vec<T> {
+        let mut result = Vec::with_capacity(self.size_hint().0);
+        result.extend(self);
+        result
+    }
+
+    fn get_current(&mut self) -> Option<&mut Box<dyn Iterator<Item = T> + 'a>> {
+        self.iters.get_mut(self.ptr)
+    }
+}
+
+impl<'a, T> Default for SeqIter<'a, T> {
+    fn default() -> Self {
+        SeqIter::new()
+    }
+}
+
+impl<'a, T> From<Vec<Box<dyn Iterator<Item=T> + 'a>>> for SeqIter<'a, T> {
+    fn from(iters: Vec<Box<dyn Iterator<Item=T> + 'a>>) -> Self {
+        SeqIter::from_vec(iters)
+    }
+}
+
+/// Consume the vectors in order.
+///
+/// ```
+/// use iterutils::SeqIter;
+/// let seq_iter = SeqIter::from(vec![vec![1, 2], vec![3]]);
+/// assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+impl<'a, T: 'a> From<Vec<Vec<T>>> for SeqIter<'a, T> {
+    fn from(vecs: Vec<Vec<T>>) -> Self {
+        SeqIter::from_vec(vecs.into_iter().map(|x| Box::new(x.into_iter()) as Box<dyn Iterator<Item=T> + 'a>).collect())
+    }
+}
+
+/// Same as `add_many`.
+impl<'a, T> Extend<Box<dyn Iterator<Item=T> + 'a>> for SeqIter<'a, T> {
+    fn extend<I: IntoIterator<Item=Box<dyn Iterator<Item=T> + 'a>>>(&mut self, iters: I) {
+        self.add_many(iters);
     }
 }
 
 /// Implementation for Iterator
-impl<T> Iterator for SeqIter<T> {
+impl<'a, T> Iterator for SeqIter<'a, T> {
     type Item = T;
     fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        let target = self.get_current();
-        if target.is_none() {
-            return None
+        // Loop instead of recursing, so long runs of empty sources can't
+        // overflow the stack
+        if self.finished {
+            return None;
+        }
+        loop {
+            let Some(target) = self.get_current() else {
+                self.finished = true;
+                return None;
+            };
+
+            let next = target.next();
+            if next.is_some() {
+                return next;
+            }
+
+            if let Some(cb) = &self.on_exhausted {
+                cb(self.ptr);
+            }
+            self.ptr += 1;
+        }
+    }
+
+    /// Sum of the hints of all sub-iterators not yet exhausted.
+    /// The upper bound is only known when every one of them has one.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            return (0, Some(0));
+        }
+        self.iters.iter().skip(self.ptr)
+            .fold((0, Some(0)), |acc, iter| add_size_hints(acc, iter.size_hint()))
+    }
+}
+
+// Combine the size hints of two iterators consumed one after the other.
+// The upper bound is lost on overflow or when either one is unknown.
+fn add_size_hints(a: (usize, Option<usize>), b: (usize, Option<usize>)) -> (usize, Option<usize>) {
+    let lower = a.0.saturating_add(b.0);
+    let upper = match (a.1, b.1) {
+        (Some(x), Some(y)) => x.checked_add(y),
+        _ => None,
+    };
+    (lower, upper)
+}
+
+/// Exhausted sub-iterators are never polled again, and once None is returned
+/// SeqIter keeps returning None, even if more iterators are added.
+impl<'a, T> FusedIterator for SeqIter<'a, T> {}
+
+/// Sub-iterators are opaque, only the position and their number are shown.
+impl<'a, T> std::fmt::Debug for SeqIter<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SeqIter")
+            .field("ptr", &self.ptr)
+            .field("sources", &self.iters.len())
+            .finish()
+    }
+}
+
+impl<'a, T> SeqIter<'a, T> {
+    /// Tag every item with the index of the sub-iterator it came from and its
+    /// position within that sub-iterator, both starting at 0.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let mut seq_iter = SeqIter::new();
+    /// seq_iter.add(Box::new(vec!['a', 'b'].into_iter()));
+    /// seq_iter.add(Box::new(vec!['c'].into_iter()));
+    /// let tagged: Vec<_> = seq_iter.enumerate_global().collect();
+    /// assert_eq!(tagged, vec![(0, 0, 'a'), (0, 1, 'b'), (1, 0, 'c')]);
+    /// ```
+    pub fn enumerate_global(self) -> EnumerateGlobal<'a, T> {
+        EnumerateGlobal { inner: self, source: None, position: 0 }
+    }
+
+    /// Count the items emitted from every sub-iterator. The returned stats are
+    /// updated while the returned iterator is consumed, also from another thread.
+    ///
+    /// ```
+    /// use iterutils::SeqIter;
+    /// let (seq_iter, stats) = SeqIter::from(vec![vec![1, 2], vec![], vec![3]]).into_with_stats();
+    /// assert_eq!(seq_iter.count(), 3);
+    /// assert_eq!(stats.items_per_source(), vec![2, 0, 1]);
+    /// ```
+    pub fn into_with_stats(self) -> (CountingSeqIter<'a, T>, Arc<SeqIterStats>) {
+        let stats = Arc::new(SeqIterStats {
+            items_per_source: (0..self.iters.len()).map(|_| AtomicUsize::new(0)).collect(),
+        });
+        (CountingSeqIter { inner: self, stats: stats.clone() }, stats)
+    }
+}
+
+/// Iterator returned by `SeqIter::enumerate_global`.
+/// Yields `(source_index, position_within_source, item)`.
+pub struct EnumerateGlobal<'a, T> {
+    inner: SeqIter<'a, T>,
+    source: Option<usize>,
+    position: usize,
+}
+
+impl<'a, T> Iterator for EnumerateGlobal<'a, T> {
+    type Item = (usize, usize, T);
+    fn next(&mut self) -> Option<(usize, usize, T)> {
+        let item = self.inner.next()?;
+        // The cursor only moves past a sub-iterator once it returned None,
+        // so it still points at the source of this item.
+        let source = self.inner.ptr;
+        if self.source != Some(source) {
+            self.source = Some(source);
+            self.position = 0;
+        }
+        let position = self.position;
+        self.position += 1;
+        Some((source, position, item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for EnumerateGlobal<'a, T> {}
+
+/// Item counts collected by the iterator returned from `SeqIter::into_with_stats`.
+#[derive(Debug)]
+pub struct SeqIterStats {
+    items_per_source: Vec<AtomicUsize>,
+}
+
+impl SeqIterStats {
+    /// Number of items emitted so far from each sub-iterator, by index.
+    pub fn items_per_source(&self) -> Vec<usize> {
+        self.items_per_source.iter().map(|x| x.load(std::sync::atomic::Ordering::Relaxed)).collect()
+    }
+
+    /// Number of items emitted so far.
+    pub fn total(&self) -> usize {
+        self.items_per_source().iter().sum()
+    }
+}
+
+/// Iterator returned by `SeqIter::into_with_stats`.
+pub struct CountingSeqIter<'a, T> {
+    inner: SeqIter<'a, T>,
+    stats: Arc<SeqIterStats>,
+}
+
+impl<'a, T> Iterator for CountingSeqIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let item = self.inner.next()?;
+        // As in EnumerateGlobal the cursor still points at the source of the item
+        self.stats.items_per_source[self.inner.ptr].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for CountingSeqIter<'a, T> {}
+
+/// SeqIter that remembers the items it emitted, so iteration can be rewound.
+/// Every emitted item is kept until `clear_cache` is called.
+/// `replay_from` rewinds it also after `next` returned None, so it is not a `FusedIterator`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::{CachedSeqIter, SeqIter};
+/// let mut s_iter = SeqIter::new();
+/// s_iter.add(Box::new(vec![1, 2, 3].into_iter()));
+/// let mut cached = CachedSeqIter::new(s_iter);
+/// assert_eq!(cached.next(), Some(1));
+/// assert_eq!(cached.next(), Some(2));
+/// cached.replay_from(0);
+/// assert_eq!(cached.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+pub struct CachedSeqIter<'a, T> {
+    inner: SeqIter<'a, T>,
+    cache: Vec<T>,
+    pos: usize,
+}
+
+impl<'a, T: Clone> CachedSeqIter<'a, T> {
+    /// Wrap a SeqIter. Items emitted before wrapping are not cached.
+    pub fn new(inner: SeqIter<'a, T>) -> CachedSeqIter<'a, T> {
+        CachedSeqIter { inner, cache: Vec::new(), pos: 0 }
+    }
+
+    /// Continue emission from the cached item at `offset`.
+    /// After the cached items are replayed, new items are taken from the SeqIter again.
+    ///
+    /// Panics if `offset` is larger than the number of cached items.
+    pub fn replay_from(&mut self, offset: usize) {
+        assert!(offset <= self.cache.len(), "replay offset {} out of bounds ({} cached items)", offset, self.cache.len());
+        self.pos = offset;
+    }
+
+    /// Drop the cached items that were already emitted.
+    /// Items still waiting to be replayed are kept, they start at offset 0 afterwards.
+    pub fn clear_cache(&mut self) {
+        self.cache.drain(..self.pos);
+        self.pos = 0;
+    }
+
+    /// Number of cached items.
+    pub fn cache_len(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+impl<'a, T: Clone> Iterator for CachedSeqIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.pos < self.cache.len() {
+            self.pos += 1;
+            return Some(self.cache[self.pos - 1].clone());
+        }
+        let next = self.inner.next()?;
+        self.cache.push(next.clone());
+        self.pos += 1;
+        Some(next)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let cached = self.cache.len() - self.pos;
+        add_size_hints((cached, Some(cached)), self.inner.size_hint())
+    }
+}
+
+// Creates a sub iterator on first use
+type IteratorThunk<T> = Box<dyn FnOnce() -> Box<dyn Iterator<Item=T>>>;
+
+/// SeqIter that creates each iterator only when the previous one is exhausted.
+/// Useful when opening a source is expensive or holds resources, e.g. files:
+/// at most one source is open at any time.
+///
+/// Example
+///
+/// ```
+/// use iterutils::LazySeqIter;
+/// let mut l_iter = LazySeqIter::new();
+/// l_iter.add(|| Box::new(vec![1, 2].into_iter()) as Box<dyn Iterator<Item = i32>>);
+/// l_iter.add(|| Box::new(vec![3].into_iter()) as Box<dyn Iterator<Item = i32>>);
+/// assert_eq!(l_iter.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+pub struct LazySeqIter<T> {
+    current: Option<Box<dyn Iterator<Item = T>>>,
+    thunks: VecDeque<IteratorThunk<T>>,
+    // Set by the first None, so iterators added afterwards are never created
+    finished: bool,
+}
+
+impl<T> LazySeqIter<T> {
+    /// Create an empty LazySeqIter.
+    pub fn new() -> LazySeqIter<T> {
+        LazySeqIter { current: None, thunks: VecDeque::new(), finished: false }
+    }
+
+    /// Add a function creating the next iterator. It is called once,
+    /// after all iterators added before are exhausted.
+    ///
+    /// Once `next` returned None the LazySeqIter is fused: the function is
+    /// dropped without being called.
+    pub fn add(&mut self, thunk: impl FnOnce() -> Box<dyn Iterator<Item=T>> + 'static) {
+        if !self.finished {
+            self.thunks.push_back(Box::new(thunk));
+        }
+    }
+
+    /// Number of iterators not created yet.
+    pub fn pending_count(&self) -> usize {
+        self.thunks.len()
+    }
+}
+
+impl<T> Default for LazySeqIter<T> {
+    fn default() -> Self {
+        LazySeqIter::new()
+    }
+}
+
+impl<T> Iterator for LazySeqIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            if let Some(next) = self.current.as_mut().and_then(|x| x.next()) {
+                return Some(next);
+            }
+            // Drop the exhausted iterator before creating the next one
+            self.current = None;
+            let Some(thunk) = self.thunks.pop_front() else {
+                self.finished = true;
+                return None;
+            };
+            self.current = Some(thunk());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.current.as_ref().map_or((0, Some(0)), |x| x.size_hint());
+        if self.thunks.is_empty() {
+            (lower, upper)
+        } else {
+            // Nothing is known about iterators not created yet
+            (lower, None)
+        }
+    }
+}
+
+/// Once all iterators are exhausted LazySeqIter keeps returning None,
+/// even if new iterators are added.
+impl<T> FusedIterator for LazySeqIter<T> {}
+
+/// Flatten iterator yields the items of every iterator produced by an outer iterator,
+/// pulling the next inner iterator only when the current one is exhausted.
+/// Like `Iterator::flatten`, for sources that are produced dynamically.
+///
+/// Example
+///
+/// ```
+/// use iterutils::FlattenMultiIter;
+/// let outer = (1..=3).map(|x| Box::new(0..x) as Box<dyn Iterator<Item = i32>>);
+/// let flat = FlattenMultiIter::new(Box::new(outer));
+/// assert_eq!(flat.collect::<Vec<_>>(), vec![0, 0, 1, 0, 1, 2]);
+/// ```
+pub struct FlattenMultiIter<T> {
+    outer: Fuse<Box<dyn Iterator<Item = Box<dyn Iterator<Item = T>>>>>,
+    current: Option<Box<dyn Iterator<Item = T>>>,
+}
+
+impl<T> FlattenMultiIter<T> {
+    /// Create a FlattenMultiIter over the iterators produced by `outer`.
+    pub fn new(outer: Box<dyn Iterator<Item=Box<dyn Iterator<Item=T>>>>) -> FlattenMultiIter<T> {
+        FlattenMultiIter { outer: outer.fuse(), current: None }
+    }
+}
+
+impl<T> Iterator for FlattenMultiIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(next) = self.current.as_mut().and_then(|x| x.next()) {
+                return Some(next);
+            }
+            self.current = None;
+            self.current = Some(self.outer.next()?);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.current.as_ref().map_or((0, Some(0)), |x| x.size_hint());
+        match self.outer.size_hint() {
+            (_, Some(0)) => (lower, upper),
+            // Nothing is known about inner iterators not produced yet
+            _ => (lower, None),
         }
+    }
+}
+
+/// The outer iterator is fused, so FlattenMultiIter keeps returning None once it is exhausted.
+impl<T> FusedIterator for FlattenMultiIter<T> {}
+
+/// Builder for SeqIter, to set up a SeqIter in one expression.
+///
+/// Example
+///
+/// ```
+/// use iterutils::SeqIterBuilder;
+/// let seq_iter = SeqIterBuilder::new()
+///     .with_capacity(2)
+///     .push(vec![1, 2].into_iter())
+///     .push(3..5)
+///     .with_on_exhausted(|i| println!("source {i} done"))
+///     .build();
+/// assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+/// ```
+pub struct SeqIterBuilder<'a, T> {
+    iters: Vec<Box<dyn Iterator<Item = T> + 'a>>,
+    on_exhausted: Option<Box<dyn Fn(usize) + 'a>>,
+}
+
+impl<'a, T> SeqIterBuilder<'a, T> {
+    /// Create a builder without iterators.
+    pub fn new() -> SeqIterBuilder<'a, T> {
+        SeqIterBuilder { iters: Vec::new(), on_exhausted: None }
+    }
+
+    /// Add an iterator, it is boxed for you.
+    pub fn push(mut self, iter: impl Iterator<Item=T> + 'a) -> Self {
+        self.iters.push(Box::new(iter));
+        self
+    }
+
+    /// Reserve room for `n` more iterators, see `SeqIter::with_capacity`.
+    pub fn with_capacity(mut self, n: usize) -> Self {
+        self.iters.reserve(n);
+        self
+    }
+
+    /// Set the callback of `SeqIter::on_source_exhausted`.
+    pub fn with_on_exhausted(mut self, cb: impl Fn(usize) + 'a) -> Self {
+        self.on_exhausted = Some(Box::new(cb));
+        self
+    }
+
+    /// Create the SeqIter.
+    pub fn build(self) -> SeqIter<'a, T> {
+        SeqIter { ptr: 0, iters: self.iters, on_exhausted: self.on_exhausted, finished: false }
+    }
+}
+
+impl<'a, T> Default for SeqIterBuilder<'a, T> {
+    fn default() -> Self {
+        SeqIterBuilder::new()
+    }
+}
+
+/// Iterator that can be cloned behind a `Box`.
+/// Implemented for every iterator that is `Clone`.
+pub trait CloneIterator: Iterator {
+    /// Clone into a new Box.
+    fn clone_box<'a>(&self) -> Box<dyn CloneIterator<Item = Self::Item> + 'a>
+        where Self: 'a;
+}
+
+impl<I: Iterator + Clone> CloneIterator for I {
+    fn clone_box<'a>(&self) -> Box<dyn CloneIterator<Item = I::Item> + 'a>
+        where Self: 'a
+    {
+        Box::new(self.clone())
+    }
+}
+
+/// SeqIter whose sub-iterators are all `Clone`, so the whole iterator can be cloned.
+/// A clone continues from the same position as the original.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ClonableSeqIter;
+/// let mut c_iter = ClonableSeqIter::new();
+/// c_iter.add(Box::new(vec![1, 2].into_iter()));
+/// c_iter.add(Box::new(3..5));
+/// assert_eq!(c_iter.next(), Some(1));
+/// let copy = c_iter.clone();
+/// assert_eq!(c_iter.collect::<Vec<_>>(), vec![2, 3, 4]);
+/// assert_eq!(copy.collect::<Vec<_>>(), vec![2, 3, 4]);
+/// ```
+pub struct ClonableSeqIter<'a, T> {
+    ptr: usize,
+    iters: Vec<Box<dyn CloneIterator<Item = T> + 'a>>,
+    // Set by the first None, so iterators added afterwards are never consumed
+    finished: bool,
+}
+
+impl<'a, T> ClonableSeqIter<'a, T> {
+    /// Create an empty ClonableSeqIter.
+    pub fn new() -> ClonableSeqIter<'a, T> {
+        ClonableSeqIter { ptr: 0, iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed clonable iterator.
+    /// Iterators added after `next` returned None are never consumed.
+    pub fn add(&mut self, iter: Box<dyn CloneIterator<Item=T> + 'a>) {
+        self.iters.push(iter);
+    }
+
+    /// Number of sub-iterators registered, including exhausted ones.
+    pub fn source_count(&self) -> usize {
+        self.iters.len()
+    }
+}
+
+impl<'a, T> Default for ClonableSeqIter<'a, T> {
+    fn default() -> Self {
+        ClonableSeqIter::new()
+    }
+}
 
-        let next = target.unwrap().next();
-        if next.is_some() {
-            return next;
+/// Exhausted sub-iterators are not cloned, so the clone starts at index 0
+/// and `source_count` may be smaller.
+impl<'a, T: 'a> Clone for ClonableSeqIter<'a, T> {
+    fn clone(&self) -> Self {
+        let iters = self.iters.iter().skip(self.ptr).map(|x| x.clone_box()).collect();
+        ClonableSeqIter { ptr: 0, iters, finished: self.finished }
+    }
+}
+
+impl<'a, T> Iterator for ClonableSeqIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.finished {
+            return None;
         }
+        while let Some(iter) = self.iters.get_mut(self.ptr) {
+            if let Some(next) = iter.next() {
+                return Some(next);
+            }
+            self.ptr += 1;
+        }
+        self.finished = true;
+        None
+    }
 
-        self.ptr += 1;
-        return self.next();
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            return (0, Some(0));
+        }
+        self.iters.iter().skip(self.ptr)
+            .fold((0, Some(0)), |acc, iter| add_size_hints(acc, iter.size_hint()))
     }
 }
 
+/// Once all sub-iterators are exhausted ClonableSeqIter keeps returning None,
+/// even if new iterators are added.
+impl<'a, T> FusedIterator for ClonableSeqIter<'a, T> {}
+
 
 /// A flexible multi iterator
 /// It allow you to use a choose function to choose item to iterator
@@ -93,12 +852,12 @@ impl<T> Iterator for SeqIter<T> {
 /// let v2: Vec<i32> = vec![1,7,8,12,44,231];
 /// let v3: Vec<i32> = vec![3,5,7,9,10,1000];
 /// let v4: [i32; 7] = [1,2,4,5,6,7,8];
-/// let choose_fn = |x:&Vec<i32>| -> Option<usize> {
+/// let choose_fn = |x:&[i32]| -> Option<usize> {
 ///     let result = x.iter().enumerate().min_by(|x, y| {
-///         x.1.cmp(&y.1)
+///         x.1.cmp(y.1)
 ///     }).map(|x| x.0);
 ///     println!("Choosing from {:?} -> {:?}", x, result);
-///     return result;
+///     result
 /// };
 /// // The choose function choses smallest (not very efficient though)
 /// let mut min_iter = MultiIterator::new(choose_fn);
@@ -113,81 +872,501 @@ impl<T> Iterator for SeqIter<T> {
 /// // Numbers will be printed in sorted order.
 /// ```
 ///
-pub struct MultiIterator<T> {
+/// The order of the head elements is not preserved: when a source is exhausted
+/// the last source is moved into its slot.
+///
+/// Sources, the choose function and the callback may borrow local data.
+///
+/// Like `SeqIter`, MultiIterator is not `Send`: the sources, the choose function
+/// and the callback are boxed without a `Send` bound.
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<iterutils::MultiIterator<i32>>();
+/// ```
+pub struct MultiIterator<'a, T> {
     head: Vec<T>,
-    iters: Vec<Box<dyn Iterator<Item = T>>>, 
-    choose_function: fn(&Vec<T>)->Option<usize>
+    iters: Vec<Box<dyn Iterator<Item = T> + 'a>>, 
+    // position of each source in add() order, kept in sync with head and iters
+    source_ids: Vec<usize>,
+    next_source_id: usize,
+    choose_function: ChooseFunction<'a, T>,
+    finished: bool,
+    on_exhausted: Option<Box<dyn Fn(usize) + 'a>>,
+    on_invalid_index: Option<Box<dyn Fn(usize) + 'a>>,
 }
 
-impl<T> MultiIterator<T> {
+/// Boxed choose function used by MultiIterator.
+/// It receives the head elements and returns the index of the one to emit.
+pub type ChooseFunction<'a, T> = Box<dyn FnMut(&[T])->Option<usize> + 'a>;
+
+impl<'a, T> MultiIterator<'a, T> {
     /// Create an empty MultiIterator with choose function
     /// When choose function returns None, the iterator ends.
+    /// It also ends, without panicking, when the choose function returns an
+    /// index past the last head element, see `with_on_invalid_index`.
     /// 
     /// The choose function chooses the an index from the head elements from the iterators.
-    pub fn new(choose_function:fn(&Vec<T>)->Option<usize>) -> MultiIterator<T> {
+    /// It may be a closure capturing state, e.g. a round robin counter.
+    pub fn new<F>(choose_function:F) -> MultiIterator<'a, T>
+        where F: FnMut(&[T])->Option<usize> + 'a
+    {
         MultiIterator {
             head:vec!(),
             iters: vec!(),
-            choose_function
+            source_ids: vec!(),
+            next_source_id: 0,
+            choose_function: Box::new(choose_function),
+            finished: false,
+            on_exhausted: None,
+            on_invalid_index: None,
         }
     }
 
-    /// Add new iterator to the list.
-    /// It does not affect elements already emitted.
-    pub fn add(&mut self, iter:Box<dyn Iterator<Item=T>>) {
-        let mut iter = iter;
-        let head = iter.next();
-        if head.is_some() {
-            self.head.push(head.unwrap());
-            self.iters.push(iter);
-        }
+    /// Create an empty MultiIterator with a plain function as choose function.
+    pub fn new_fn(choose_function:fn(&[T])->Option<usize>) -> MultiIterator<'a, T>
+        where T: 'a
+    {
+        MultiIterator::new(choose_function)
     }
 
-    fn choose(&mut self)->Option<T> {
-        if self.head.len() == 0 {
+    /// Create an empty MultiIterator that always emits the head with the
+    /// smallest key. With sorted sources this yields a globally sorted merge.
+    /// Ties go to the first such head element.
+    pub fn min_by_key<K: Ord + 'a>(key: fn(&T)->K) -> MultiIterator<'a, T>
+        where T: 'a
+    {
+        MultiIterator::new(move |heads: &[T]| {
+            heads.iter().enumerate().min_by_key(|x| key(x.1)).map(|x| x.0)
+        })
+    }
+
+    /// Create an empty MultiIterator that always emits the head with the
+    /// largest key. Ties go to the first such head element.
+    pub fn max_by_key<K: Ord + 'a>(key: fn(&T)->K) -> MultiIterator<'a, T>
+        where T: 'a
+    {
+        MultiIterator::new(move |heads: &[T]| {
+            // max_by_key returns the last maximum, so scan backwards
+            heads.iter().enumerate().rev().max_by_key(|x| key(x.1)).map(|x| x.0)
+        })
+    }
+
+    /// Add new iterator to the list.
+    /// It does not affect elements already emitted.
+    /// Adding while iterating is supported, but once `next` returned None the
+    /// MultiIterator is fused: the source is silently dropped without being
+    /// advanced. Earlier versions resumed iteration with it instead.
+    ///
+    /// The iterator is advanced once to buffer its head element. An empty
+    /// iterator is silently dropped: it is not counted by `len`, the choose
+    /// function never sees it and an ended MultiIterator stays ended. It
+    /// still takes a position in `add` order, as seen by `with_on_exhausted`.
+    pub fn add(&mut self, iter:Box<dyn Iterator<Item=T> + 'a>) {
+        let mut iter = iter;
+        let source_id = self.next_source_id;
+        self.next_source_id += 1;
+        if self.finished {
+            return;
+        }
+        if let Some(head) = iter.next() {
+            self.head.push(head);
+            self.iters.push(iter);
+            self.source_ids.push(source_id);
+        }
+    }
+
+    /// Add several iterators, same as calling `add` for each in order.
+    /// Every iterator is advanced once to buffer its head element, unless
+    /// the MultiIterator has ended.
+    pub fn add_many(&mut self, iters: impl IntoIterator<Item=Box<dyn Iterator<Item=T> + 'a>>) {
+        for iter in iters {
+            self.add(iter);
+        }
+    }
+
+    /// Add a Boxed iterator of which only the items matching `pred` are used.
+    ///
+    /// ```
+    /// use iterutils::MultiIterator;
+    /// let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+    /// m_iter.add_with_filter(Box::new(1..10), Box::new(|x| x % 3 == 0));
+    /// m_iter.add_with_filter(Box::new(1..10), Box::new(|x| x % 4 == 0));
+    /// assert_eq!(m_iter.collect::<Vec<_>>(), vec![3, 4, 6, 8, 9]);
+    /// ```
+    pub fn add_with_filter(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>, pred: Box<dyn Fn(&T)->bool + 'a>)
+        where T: 'a
+    {
+        self.add(Box::new(iter.filter(move |x| pred(x))));
+    }
+
+    /// Add a Boxed iterator of another item type, converting its items with `f`.
+    ///
+    /// ```
+    /// use iterutils::MultiIterator;
+    /// let mut m_iter = MultiIterator::min_by_key(|x: &i64| *x);
+    /// m_iter.add(Box::new(vec![2i64, 5].into_iter()));
+    /// m_iter.add_mapped(Box::new(vec![1u8, 3].into_iter()), i64::from);
+    /// m_iter.add_mapped(Box::new(vec!["4"].into_iter()), |x| x.parse().unwrap());
+    /// assert_eq!(m_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    /// ```
+    pub fn add_mapped<U: 'a>(&mut self, iter: Box<dyn Iterator<Item=U> + 'a>, f: fn(U) -> T)
+        where T: 'a
+    {
+        self.add(Box::new(iter.map(f)));
+    }
+
+    /// Number of source iterators still active.
+    /// Exhausted sources are dropped, so this shrinks during iteration.
+    pub fn len(&self) -> usize {
+        self.iters.len()
+    }
+
+    /// Replace the choose function, keeping all sources and buffered heads.
+    /// If the previous choose function had ended the iteration, it stays ended.
+    pub fn replace_choose_fn(&mut self, new_fn: ChooseFunction<'a, T>) {
+        self.choose_function = new_fn;
+    }
+
+    /// Register a callback invoked when a source runs dry during iteration.
+    ///
+    /// The callback receives the position of the source in `add` order
+    /// (starting at 0, counting every `add` call), which unlike its slot does
+    /// not change when other sources are removed. Sources that are empty when
+    /// added, or dropped with `remove_source`, are not reported.
+    pub fn with_on_exhausted(mut self, cb: impl Fn(usize) + 'a) -> MultiIterator<'a, T> {
+        self.on_exhausted = Some(Box::new(cb));
+        self
+    }
+
+    /// Register a callback invoked with the index when the choose function
+    /// returns one that is out of bounds. The iterator ends either way.
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use iterutils::MultiIterator;
+    /// let bad = Cell::new(None);
+    /// let mut m_iter = MultiIterator::new(|_: &[i32]| Some(5))
+    ///     .with_on_invalid_index(|i| bad.set(Some(i)));
+    /// m_iter.add(Box::new(vec![1, 2].into_iter()));
+    /// assert_eq!(m_iter.next(), None);
+    /// assert_eq!(bad.get(), Some(5));
+    /// ```
+    pub fn with_on_invalid_index(mut self, cb: impl Fn(usize) + 'a) -> MultiIterator<'a, T> {
+        self.on_invalid_index = Some(Box::new(cb));
+        self
+    }
+
+    /// True when no source iterator is active.
+    pub fn is_empty(&self) -> bool {
+        self.iters.is_empty()
+    }
+
+    /// The head elements the choose function will be given on the next call,
+    /// one per active source. Empty once all sources are exhausted.
+    pub fn head_snapshot(&self) -> &[T] {
+        &self.head
+    }
+
+    /// Drop the source at `index` (as in `head_snapshot()`) together with its
+    /// buffered head element.
+    ///
+    /// The last source is moved into the freed slot, so indexes of the other
+    /// sources may change.
+    pub fn remove_source(&mut self, index: usize) -> Result<(), IndexError> {
+        if index >= self.iters.len() {
+            return Err(IndexError { index, len: self.iters.len() });
+        }
+        let _ = self.iters.swap_remove(index);
+        let _ = self.head.swap_remove(index);
+        let _ = self.source_ids.swap_remove(index);
+        Ok(())
+    }
+
+    /// Tag every item with the position of its source in `add` order
+    /// (counting every `add` call from 0). The tag does not change when other
+    /// sources are exhausted or removed.
+    ///
+    /// ```
+    /// use iterutils::MultiIterator;
+    /// let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+    /// m_iter.add(Box::new(vec![1, 4].into_iter()));
+    /// m_iter.add(Box::new(vec![2, 3].into_iter()));
+    /// let tagged: Vec<_> = m_iter.enumerate_sources().collect();
+    /// assert_eq!(tagged, vec![(0, 1), (1, 2), (1, 3), (0, 4)]);
+    /// ```
+    pub fn enumerate_sources(self) -> SourceTaggedMultiIterator<'a, T> {
+        SourceTaggedMultiIterator { inner: self }
+    }
+
+    /// Count how often each source is chosen, to spot unbalanced sources.
+    /// Sources are numbered in `add` order like in `enumerate_sources`.
+    /// The returned stats are updated while the returned iterator is consumed.
+    ///
+    /// ```
+    /// use iterutils::MultiIterator;
+    /// let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+    /// m_iter.add(Box::new(vec![1, 2, 3].into_iter()));
+    /// m_iter.add(Box::new(vec![10].into_iter()));
+    /// let (m_iter, stats) = m_iter.into_with_stats();
+    /// assert_eq!(m_iter.count(), 4);
+    /// assert_eq!(stats.chosen_counts(), vec![3, 1]);
+    /// ```
+    pub fn into_with_stats(self) -> (CountingMultiIterator<'a, T>, Arc<MultiIteratorStats>) {
+        let stats = Arc::new(MultiIteratorStats {
+            chosen_counts: (0..self.next_source_id).map(|_| AtomicUsize::new(0)).collect(),
+        });
+        (CountingMultiIterator { inner: self, stats: stats.clone() }, stats)
+    }
+
+    /// Collect the remaining items into a `Vec`, allocating room for the
+    /// buffered heads up front.
+    pub fn collect_vec(self) -> Vec<T> {
+        let mut result = Vec::with_capacity(self.head.len());
+        result.extend(self);
+        result
+    }
+
+    fn next_tagged(&mut self) -> Option<(usize, T)> {
+        if self.finished {
             return None;
         }
-        // Check selected index. If the index is not selected, return end of iterator
-        let index = (self.choose_function)(&self.head);
-        if index.is_none() {
+        let next = self.choose();
+        // A stateful choose function could pick again after returning None,
+        // so remember that the iterator ended.
+        self.finished = next.is_none();
+        next
+    }
+
+    fn choose(&mut self)->Option<(usize, T)> {
+        if self.head.is_empty() {
             return None;
         }
-        // Find the index
-        let index = index.unwrap();
+        // Check selected index. If the index is not selected, return end of iterator
+        let index = (self.choose_function)(&self.head)?;
 
         // If the index is invalid, return None
-        if index > self.head.len() - 1 {
+        if index >= self.head.len() {
+            if let Some(cb) = &self.on_invalid_index {
+                cb(index);
+            }
             return None;
         }
 
         // Get the next item at the same location
         let iter = self.iters.get_mut(index);
-        let iter_next = iter.unwrap().next();
-        if iter_next.is_none() {
-            let _ = self.iters.remove(index);
-            let removed = self.head.remove(index);
-            
-            // return head[index]
-            return Some(removed);
-        } else {
-            // Swap the result in
-            let mut next_elem = iter_next.unwrap();
-            std::mem::swap(&mut self.head[index], &mut next_elem);
-            return Some(next_elem);
+        match iter.unwrap().next() {
+            None => {
+                // The last source takes the freed slot. O(1) unlike remove,
+                // and fine since slot order is not part of the contract and
+                // the source id moves along with it.
+                let _ = self.iters.swap_remove(index);
+                let removed = self.head.swap_remove(index);
+                let source_id = self.source_ids.swap_remove(index);
+                if let Some(cb) = &self.on_exhausted {
+                    cb(source_id);
+                }
+
+                // return head[index]
+                Some((source_id, removed))
+            }
+            Some(mut next_elem) => {
+                // Swap the result in
+                std::mem::swap(&mut self.head[index], &mut next_elem);
+                Some((self.source_ids[index], next_elem))
+            }
         }
     }
 }
 
 
 /// Iterator implementation for MultiIterator
-impl<T> Iterator for MultiIterator<T> {
+impl<'a, T> Iterator for MultiIterator<'a, T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
-        return self.choose();
+        self.next_tagged().map(|x| x.1)
+    }
+
+    /// The upper bound is the buffered head elements plus the upper bounds
+    /// of all sources, when they all have one. The lower bound is always 0
+    /// because the choose function may end the iteration at any time.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            return (0, Some(0));
+        }
+        let buffered = self.head.len();
+        let (_, upper) = self.iters.iter()
+            .fold((buffered, Some(buffered)), |acc, iter| add_size_hints(acc, iter.size_hint()));
+        (0, upper)
+    }
+}
+
+/// Once None is returned, MultiIterator keeps returning None, even if
+/// sources are added or the choose function is replaced.
+impl<'a, T> FusedIterator for MultiIterator<'a, T> {}
+
+/// Shows the head elements as the choose function sees them, and for each
+/// one the `add` position of its source, since exhausted sources reorder the heads.
+impl<'a, T: std::fmt::Debug> std::fmt::Debug for MultiIterator<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiIterator")
+            .field("sources", &self.iters.len())
+            .field("head", &self.head)
+            .field("source_ids", &self.source_ids)
+            .finish()
+    }
+}
+
+/// Iterator returned by `MultiIterator::enumerate_sources`.
+/// Yields `(source_index, item)`.
+pub struct SourceTaggedMultiIterator<'a, T> {
+    inner: MultiIterator<'a, T>,
+}
+
+impl<'a, T> SourceTaggedMultiIterator<'a, T> {
+    /// Add a Boxed iterator. Its items are tagged with the next `add` position.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>) {
+        self.inner.add(iter);
+    }
+
+    /// Same as `MultiIterator::remove_source`. Tags of the remaining sources do not change.
+    ///
+    /// ```
+    /// use iterutils::MultiIterator;
+    /// let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+    /// m_iter.add(Box::new(vec![1, 4].into_iter()));
+    /// m_iter.add(Box::new(vec![2, 3].into_iter()));
+    /// let mut tagged = m_iter.enumerate_sources();
+    /// tagged.remove_source(0).unwrap();
+    /// assert_eq!(tagged.collect::<Vec<_>>(), vec![(1, 2), (1, 3)]);
+    /// ```
+    pub fn remove_source(&mut self, index: usize) -> Result<(), IndexError> {
+        self.inner.remove_source(index)
+    }
+}
+
+impl<'a, T> Iterator for SourceTaggedMultiIterator<'a, T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        self.inner.next_tagged()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for SourceTaggedMultiIterator<'a, T> {}
+
+/// Counts collected by the iterator returned from `MultiIterator::into_with_stats`.
+#[derive(Debug)]
+pub struct MultiIteratorStats {
+    chosen_counts: Vec<AtomicUsize>,
+}
+
+impl MultiIteratorStats {
+    /// Number of times each source was chosen so far, by `add` position.
+    pub fn chosen_counts(&self) -> Vec<usize> {
+        self.chosen_counts.iter().map(|x| x.load(std::sync::atomic::Ordering::Relaxed)).collect()
+    }
+}
+
+/// Iterator returned by `MultiIterator::into_with_stats`.
+pub struct CountingMultiIterator<'a, T> {
+    inner: MultiIterator<'a, T>,
+    stats: Arc<MultiIteratorStats>,
+}
+
+impl<'a, T> Iterator for CountingMultiIterator<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let (source_id, item) = self.inner.next_tagged()?;
+        self.stats.chosen_counts[source_id].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for CountingMultiIterator<'a, T> {}
+
+/// Scan over a MultiIterator with one state per source, like `Iterator::scan`.
+/// Every item is passed to the scan function together with the state of its source;
+/// `states[i]` belongs to the source added i-th (counting every `add` call from 0).
+/// When the scan function returns None, that source is removed and the others go on.
+///
+/// Example
+///
+/// ```
+/// use iterutils::{MultiIterator, ScanMultiIter};
+/// let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+/// m_iter.add(Box::new(vec![1, 3, 5].into_iter()));
+/// m_iter.add(Box::new(vec![2, 4].into_iter()));
+/// // Running sum per source
+/// let scan = ScanMultiIter::new(m_iter, vec![0, 100], |sum, x| { *sum += x; Some(*sum) });
+/// assert_eq!(scan.collect::<Vec<_>>(), vec![1, 102, 4, 106, 9]);
+/// ```
+pub struct ScanMultiIter<'a, T, S, U> {
+    inner: MultiIterator<'a, T>,
+    states: Vec<S>,
+    scan: fn(&mut S, T) -> Option<U>,
+}
+
+impl<'a, T, S, U> ScanMultiIter<'a, T, S, U> {
+    /// Create a ScanMultiIter. `states` must hold a state for every source of `inner`.
+    ///
+    /// Panics if there are fewer states than sources ever added to `inner`.
+    pub fn new(inner: MultiIterator<'a, T>, states: Vec<S>, scan: fn(&mut S, T) -> Option<U>) -> ScanMultiIter<'a, T, S, U> {
+        assert!(states.len() >= inner.next_source_id,
+            "no scan state for every source ({} states, {} sources)", states.len(), inner.next_source_id);
+        ScanMultiIter { inner, states, scan }
     }
 }
 
+impl<'a, T, S, U> Iterator for ScanMultiIter<'a, T, S, U> {
+    type Item = U;
+    fn next(&mut self) -> Option<U> {
+        loop {
+            let (source_id, item) = self.inner.next_tagged()?;
+            // new checked there is a state for every source
+            if let Some(next) = (self.scan)(&mut self.states[source_id], item) {
+                return Some(next);
+            }
+            // The source may be exhausted already and gone
+            if let Some(index) = self.inner.source_ids.iter().position(|x| *x == source_id) {
+                let _ = self.inner.remove_source(index);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, S, U> FusedIterator for ScanMultiIter<'a, T, S, U> {}
+
+/// Error returned when a source index is out of bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexError {
+    /// The requested index
+    pub index: usize,
+    /// Number of sources at the time of the request
+    pub len: usize,
+}
+
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source index {} out of bounds ({} sources)", self.index, self.len)
+    }
+}
+
+impl std::error::Error for IndexError {}
+
 
 /// A special ordered iterator that helps you to iterate elements with global 
 /// order. 
@@ -213,78 +1392,367 @@ impl<T> Iterator for MultiIterator<T> {
 /// }
 /// ```
 /// 
-/// `OrderedIterator` only support `Ord` items.
+/// `new_min` and `new_max` require `Ord` items. Other items (e.g. `f64`) can be
+/// merged with `new_with_comparator`.
 /// 
 /// Internally it uses min/max heap to select. This is more efficient than MultiIterator typically.
 /// But MultiIterator can achieve something this iterator can't achieve.
-pub struct OrderedIterator<T> 
-    where T:Ord
-{
-    comparator: fn(&T, &T) -> Ordering,
-    head: BinaryHeap<HeapItem<T>>,
-    iters: Vec<Box<dyn Iterator<Item = T>>>, 
+///
+/// Sources may borrow local data, e.g. slice iterators.
+///
+/// Equal items are emitted in a deterministic order: by source slot, where
+/// a slot freed by an exhausted source is reused by the next `add`. The
+/// `*_stable` constructors use `add` order instead.
+///
+/// OrderedIterator is not `Send` either. Besides the boxed sources, the
+/// comparator is shared with every heap item through an `Rc`.
+///
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<iterutils::OrderedIterator<i32>>();
+/// ```
+pub struct OrderedIterator<'a, T> {
+    // Emission order: Less is emitted first
+    comparator: Comparator<'a, T>,
+    // Break ties by add() order
+    stable: bool,
+    // Stop when a source is found out of order
+    checked: bool,
+    failed: bool,
+    error: Option<UnsortedInputError>,
+    // Set by the first None, so sources added afterwards are never consumed
+    finished: bool,
+    add_count: usize,
+    head: BinaryHeap<HeapItem<'a, T>>,
+    // Exhausted sources are dropped and their slot is reused by the next add()
+    iters: Vec<Option<Box<dyn Iterator<Item = T> + 'a>>>, 
+    free_slots: Vec<usize>,
 }
 
-struct HeapItem<T> {
+// Shared by the iterator and all its heap items
+type Comparator<'a, T> = Rc<dyn Fn(&T, &T) -> Ordering + 'a>;
+
+struct HeapItem<'a, T> {
     what:T,
     iter_index: usize,
-    comparator: fn(&T,&T)->std::cmp::Ordering
+    // Position of the source in add() order
+    source_order: usize,
+    stable: bool,
+    comparator: Comparator<'a, T>
 }
 
-impl<T> Ord for HeapItem<T> {
+impl<'a, T> Ord for HeapItem<'a, T> {
     fn cmp(&self, other:&Self) -> Ordering {
-        (self.comparator)(&self.what, &other.what)
+        // BinaryHeap pops the greatest item, so reverse the emission order
+        let result = (self.comparator)(&other.what, &self.what);
+        // Never leave ties to the heap, its order for equal items is unspecified
+        if self.stable {
+            result.then(other.source_order.cmp(&self.source_order))
+        } else {
+            result.then(other.iter_index.cmp(&self.iter_index))
+        }
     }
 }
 
-impl<T> PartialEq for HeapItem<T> {
+impl<'a, T> PartialEq for HeapItem<'a, T> {
     fn eq(&self, other: &Self) -> bool {
-        (self.comparator)(&self.what, &other.what) == Ordering::Equal
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl<T> Eq for HeapItem<T> {
+impl<'a, T> Eq for HeapItem<'a, T> {
 }
 
-impl<T> PartialOrd for HeapItem<T> {
+impl<'a, T> PartialOrd for HeapItem<'a, T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
 /// Iterator implementation for OrderedIterator
-impl<T> Iterator for OrderedIterator<T> 
-    where T:Ord
-{
+impl<'a, T> Iterator for OrderedIterator<'a, T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
         self.choose()
     }
+
+    /// The heap holds exactly one item per active source, so this is the
+    /// heap size plus the hints of all sources. Exact when every source has
+    /// an exact hint.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.failed || self.finished {
+            return (0, Some(0));
+        }
+        let buffered = self.head.len();
+        let (lower, upper) = self.iters.iter().flatten()
+            .fold((buffered, Some(buffered)), |acc, iter| add_size_hints(acc, iter.size_hint()));
+        // A checked iterator may end early on unsorted input
+        if self.checked {
+            (lower.min(1), upper)
+        } else {
+            (lower, upper)
+        }
+    }
+}
+
+/// Once the heap is empty OrderedIterator keeps returning None, even if
+/// sources are added.
+impl<'a, T> FusedIterator for OrderedIterator<'a, T> {}
+
+/// Same as `OrderedIterator::from_sorted_vecs`.
+/// In debug builds it panics if a vector is not sorted ascending.
+impl<'a, T: Ord + 'a> From<Vec<Vec<T>>> for OrderedIterator<'a, T> {
+    fn from(vecs: Vec<Vec<T>>) -> Self {
+        for (i, v) in vecs.iter().enumerate() {
+            debug_assert!(v.windows(2).all(|x| x[0] <= x[1]), "vector {} is not sorted", i);
+        }
+        OrderedIterator::from_sorted_vecs(vecs)
+    }
+}
+
+/// Same as `add_many`, also while iterating, until `next` returned None.
+impl<'a, T> Extend<Box<dyn Iterator<Item=T> + 'a>> for OrderedIterator<'a, T> {
+    fn extend<I: IntoIterator<Item=Box<dyn Iterator<Item=T> + 'a>>>(&mut self, iters: I) {
+        self.add_many(iters);
+    }
+}
+
+/// `next` is the item emitted next. Heap contents are listed in heap order,
+/// not in emission order.
+impl<'a, T: std::fmt::Debug> std::fmt::Debug for OrderedIterator<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderedIterator")
+            .field("sources", &self.source_count())
+            .field("next", &self.peek())
+            .field("heap", &self.head)
+            .finish()
+    }
+}
+
+impl<'a, T: std::fmt::Debug> std::fmt::Debug for HeapItem<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeapItem")
+            .field("what", &self.what)
+            .field("iter_index", &self.iter_index)
+            .finish()
+    }
 }
-impl<T> OrderedIterator<T> 
-    where T:Ord
+impl<'a, T> OrderedIterator<'a, T> 
+    where T:Ord + 'a
 {
     /// Create a new min iterator that iterates item from small to large
-    pub fn new_min() -> OrderedIterator<T> {
+    pub fn new_min() -> OrderedIterator<'a, T> {
         let comparator = |x:&T, y:&T| {
-            y.cmp(&x)
+            x.cmp(y)
         };
-        OrderedIterator {
-            comparator,
-            head: BinaryHeap::new(),
-            iters: vec!(),
-        }
+        OrderedIterator::new_with_comparator(comparator)
     }
 
     /// Create new iterator that iterators elements from large to small
-    pub fn new_max() -> OrderedIterator<T> {
+    pub fn new_max() -> OrderedIterator<'a, T> {
         let comparator = |x:&T, y:&T| {
-            x.cmp(&y)
+            y.cmp(x)
         };
+        OrderedIterator::new_with_comparator(comparator)
+    }
+
+    /// Same as `new_min`, but verifies that every source is sorted.
+    ///
+    /// When a source yields an item smaller than its previous one, the
+    /// iterator ends and the problem can be retrieved with `take_error`.
+    /// Items emitted before that are correctly ordered.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let mut o_iter = OrderedIterator::new_min_checked();
+    /// o_iter.add(Box::new(vec![1, 3, 2].into_iter()));
+    /// assert_eq!(o_iter.by_ref().collect::<Vec<_>>(), vec![1, 3]);
+    /// assert_eq!(o_iter.take_error().map(|e| e.source_index), Some(0));
+    /// ```
+    pub fn new_min_checked() -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::new_min();
+        result.checked = true;
+        result
+    }
+
+    /// Merge vectors sorted in ascending order.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1, 4], vec![2, 3]]);
+    /// assert_eq!(o_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    /// ```
+    pub fn from_sorted_vecs(vecs: Vec<Vec<T>>) -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::new_min();
+        for v in vecs {
+            result.add(Box::new(v.into_iter()));
+        }
+        result
+    }
+
+    /// Merge vectors sorted in descending order.
+    pub fn from_sorted_vecs_desc(vecs: Vec<Vec<T>>) -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::new_max();
+        for v in vecs {
+            result.add(Box::new(v.into_iter()));
+        }
+        result
+    }
+
+    /// Same as `new_min`, but equal items are emitted in the order their
+    /// sources were added, even when sources are added during iteration.
+    pub fn new_min_stable() -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::new_min();
+        result.stable = true;
+        result
+    }
+
+    /// Same as `new_max`, but equal items are emitted in the order their
+    /// sources were added, even when sources are added during iteration.
+    pub fn new_max_stable() -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::new_max();
+        result.stable = true;
+        result
+    }
+
+    /// Start building an OrderedIterator that emits items from small to large.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let o_iter = OrderedIterator::builder_min()
+    ///     .add(vec![1, 4].into_iter())
+    ///     .add(vec![2, 3].into_iter())
+    ///     .check_sorted(true)
+    ///     .build();
+    /// assert_eq!(o_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    /// ```
+    pub fn builder_min() -> OrderedIteratorBuilder<'a, T> {
+        OrderedIteratorBuilder::new(Rc::new(|x: &T, y: &T| x.cmp(y)))
+    }
+
+    /// Start building an OrderedIterator that emits items from large to small.
+    pub fn builder_max() -> OrderedIteratorBuilder<'a, T> {
+        OrderedIteratorBuilder::new(Rc::new(|x: &T, y: &T| y.cmp(x)))
+    }
+}
+
+/// Builder for OrderedIterator, created by `OrderedIterator::builder_min`,
+/// `builder_max` or `builder_with_comparator`.
+/// Sources are added when `build` is called, so all options apply to them
+/// no matter in which order they are set.
+pub struct OrderedIteratorBuilder<'a, T> {
+    comparator: Comparator<'a, T>,
+    stable: bool,
+    checked: bool,
+    iters: Vec<Box<dyn Iterator<Item = T> + 'a>>,
+}
+
+impl<'a, T> OrderedIteratorBuilder<'a, T> {
+    fn new(comparator: Comparator<'a, T>) -> OrderedIteratorBuilder<'a, T> {
+        OrderedIteratorBuilder { comparator, stable: false, checked: false, iters: Vec::new() }
+    }
+
+    /// Add a sorted iterator, it is boxed for you.
+    // Named like OrderedIterator::add, this is not an addition of builders
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, iter: impl Iterator<Item=T> + 'a) -> Self {
+        self.iters.push(Box::new(iter));
+        self
+    }
+
+    /// Replace the emission order, see `OrderedIterator::new_with_comparator`.
+    pub fn comparator(mut self, cmp: fn(&T, &T) -> Ordering) -> Self
+        where T: 'a
+    {
+        self.comparator = Rc::new(cmp);
+        self
+    }
+
+    /// Emit equal items in the order their sources were added, see `OrderedIterator::new_min_stable`.
+    pub fn stable(mut self, stable: bool) -> Self {
+        self.stable = stable;
+        self
+    }
+
+    /// Verify that every source is sorted, see `OrderedIterator::new_min_checked`.
+    pub fn check_sorted(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
+    }
+
+    /// Create the OrderedIterator.
+    pub fn build(self) -> OrderedIterator<'a, T> {
+        let mut result = OrderedIterator::from_comparator(self.comparator);
+        result.stable = self.stable;
+        result.checked = self.checked;
+        result.add_many(self.iters);
+        result
+    }
+}
+
+impl<'a, T> OrderedIterator<'a, T> {
+    /// Create a new iterator that emits items in the order defined by `cmp`:
+    /// an item comparing `Less` is emitted first.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let mut o_iter = OrderedIterator::new_with_comparator(|x: &f64, y: &f64| x.total_cmp(y));
+    /// o_iter.add(Box::new(vec![0.5, 2.5].into_iter()));
+    /// o_iter.add(Box::new(vec![1.5].into_iter()));
+    /// assert_eq!(o_iter.collect::<Vec<_>>(), vec![0.5, 1.5, 2.5]);
+    /// ```
+    pub fn new_with_comparator(cmp: fn(&T, &T) -> Ordering) -> OrderedIterator<'a, T>
+        where T: 'a
+    {
+        OrderedIterator::from_comparator(Rc::new(cmp))
+    }
+
+    /// Start building an OrderedIterator that emits items in the order
+    /// defined by `cmp`, like `new_with_comparator`. Items need not be `Ord`.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let o_iter = OrderedIterator::builder_with_comparator(|x: &f64, y: &f64| x.total_cmp(y))
+    ///     .add(vec![0.5, 2.5].into_iter())
+    ///     .add(vec![1.5, 1.5].into_iter())
+    ///     .check_sorted(true)
+    ///     .build();
+    /// assert_eq!(o_iter.collect::<Vec<_>>(), vec![0.5, 1.5, 1.5, 2.5]);
+    /// ```
+    pub fn builder_with_comparator(cmp: fn(&T, &T) -> Ordering) -> OrderedIteratorBuilder<'a, T>
+        where T: 'a
+    {
+        OrderedIteratorBuilder::new(Rc::new(cmp))
+    }
+
+    /// Create a new iterator that emits items in ascending order of `key`.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let mut o_iter = OrderedIterator::new_by_key(|x: &(u32, &str)| x.0);
+    /// o_iter.add(Box::new(vec![(1, "b"), (3, "a")].into_iter()));
+    /// o_iter.add(Box::new(vec![(2, "c")].into_iter()));
+    /// let names: Vec<_> = o_iter.map(|x| x.1).collect();
+    /// assert_eq!(names, vec!["b", "c", "a"]);
+    /// ```
+    pub fn new_by_key<K: Ord + 'a>(key: fn(&T)->K) -> OrderedIterator<'a, T>
+        where T: 'a
+    {
+        OrderedIterator::from_comparator(Rc::new(move |x: &T, y: &T| key(x).cmp(&key(y))))
+    }
+
+    fn from_comparator(comparator: Comparator<'a, T>) -> OrderedIterator<'a, T> {
         OrderedIterator {
             comparator,
+            stable: false,
+            checked: false,
+            failed: false,
+            error: None,
+            finished: false,
+            add_count: 0,
             head: BinaryHeap::new(),
             iters: vec!(),
+            free_slots: vec!(),
         }
     }
 
@@ -292,130 +1760,3307 @@ impl<T> OrderedIterator<T>
     /// 
     /// For min iterator, sort elements in Ascending before adding
     /// For max iterator, sort elements in Descending before adding
-    pub fn add(&mut self, iter:Box<dyn Iterator<Item=T>>) {
+    /// For a custom comparator, sort elements with the same comparator
+    ///
+    /// Adding while iterating is supported, but once `next` returned None the
+    /// OrderedIterator is fused: the source is silently dropped without being
+    /// advanced. Earlier versions resumed iteration with it instead.
+    pub fn add(&mut self, iter:Box<dyn Iterator<Item=T> + 'a>) {
         let mut iter = iter;
-        let head = iter.next();
-        if head.is_some() {
-            let head = head.unwrap();
+        let source_order = self.add_count;
+        self.add_count += 1;
+        if self.finished {
+            return;
+        }
+        if let Some(head) = iter.next() {
+            let iter_index = match self.free_slots.pop() {
+                Some(slot) => {
+                    self.iters[slot] = Some(iter);
+                    slot
+                }
+                None => {
+                    self.iters.push(Some(iter));
+                    self.iters.len() - 1
+                }
+            };
             let item = HeapItem {
                 what: head,
-                comparator: self.comparator,
-                iter_index: self.iters.len(),
+                comparator: self.comparator.clone(),
+                iter_index,
+                source_order,
+                stable: self.stable,
             };
             self.head.push(item);
-            self.iters.push(iter);
         }
     }
 
-    fn choose(&mut self)->Option<T> {
-        if self.head.len() == 0 {
+    /// Add several ordered iterators, same as calling `add` for each in order.
+    pub fn add_many(&mut self, iters: impl IntoIterator<Item=Box<dyn Iterator<Item=T> + 'a>>) {
+        for iter in iters {
+            self.add(iter);
+        }
+    }
+
+    /// Number of sources not exhausted yet. The heap holds exactly one item
+    /// for each of them. Always 0 once the iterator has ended.
+    pub fn source_count(&self) -> usize {
+        if self.finished {
+            return 0;
+        }
+        self.head.len()
+    }
+
+    /// The item the next call to `next()` will return, without consuming it.
+    pub fn peek(&self) -> Option<&T> {
+        if self.finished {
             return None;
         }
-        // Check selected index. If the index is not selected, return end of iterator
-        let chosen = self.head.pop();
-        if chosen.is_none() {
+        self.head.peek().map(|x| &x.what)
+    }
+
+    /// Collect the remaining items into a `Vec`, allocating room for the
+    /// size hint's lower bound up front.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1, 3], vec![2]]);
+    /// assert_eq!(o_iter.collect_vec(), vec![1, 2, 3]);
+    /// ```
+    pub fn collect_vec(self) -> Vec<T> {
+        let mut result = Vec::with_capacity(self.size_hint().0);
+        result.extend(self);
+        result
+    }
+
+    /// Emit up to `k` items at once, fewer if the iterator ends first.
+    /// Every item still costs one heap pop, but the result is allocated only once.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let mut o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1, 3], vec![2]]);
+    /// assert_eq!(o_iter.batch_next(2), vec![1, 2]);
+    /// assert_eq!(o_iter.batch_next(2), vec![3]);
+    /// assert!(o_iter.batch_next(2).is_empty());
+    /// ```
+    pub fn batch_next(&mut self, k: usize) -> Vec<T> {
+        let available = self.size_hint().0.max(self.head.len());
+        let mut result = Vec::with_capacity(k.min(available));
+        result.extend(self.by_ref().take(k));
+        result
+    }
+
+    /// Emit each distinct value once, skipping items equal to the previous one.
+    /// Since the merge is sorted, this removes duplicates within and across
+    /// sources.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1, 2, 2], vec![2, 3]]);
+    /// assert_eq!(o_iter.dedup().collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// ```
+    pub fn dedup(self) -> DedupOrderedIterator<'a, T>
+        where T: PartialEq + 'a
+    {
+        self.dedup_by(|x, y| x == y)
+    }
+
+    /// Emit only the first of consecutive items with the same key.
+    ///
+    /// ```
+    /// use iterutils::OrderedIterator;
+    /// let mut o_iter = OrderedIterator::new_by_key(|x: &(u32, char)| x.0);
+    /// o_iter.add(Box::new(vec![(1, 'a'), (2, 'a')].into_iter()));
+    /// o_iter.add(Box::new(vec![(1, 'b'), (3, 'b')].into_iter()));
+    /// let firsts: Vec<_> = o_iter.dedup_by_key(|x| x.0).collect();
+    /// assert_eq!(firsts, vec![(1, 'a'), (2, 'a'), (3, 'b')]);
+    /// ```
+    pub fn dedup_by_key<K: PartialEq + 'a>(self, key: fn(&T)->K) -> DedupOrderedIterator<'a, T>
+        where T: 'a
+    {
+        DedupOrderedIterator { inner: self, same: Box::new(move |x, y| key(x) == key(y)) }
+    }
+
+    /// Emit only the first of consecutive items for which `eq` returns true.
+    /// `eq` receives the emitted item, then the candidate.
+    pub fn dedup_by(self, eq: fn(&T, &T)->bool) -> DedupOrderedIterator<'a, T>
+        where T: 'a
+    {
+        DedupOrderedIterator { inner: self, same: Box::new(eq) }
+    }
+
+    /// Call `cb` with the number of items emitted so far after every
+    /// `interval` items. Nothing but the count is kept.
+    ///
+    /// Panics if `interval` is 0.
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    /// use iterutils::OrderedIterator;
+    /// let reports = RefCell::new(Vec::new());
+    /// let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1, 3, 5], vec![2, 4]]);
+    /// let count = o_iter.with_progress(2, |n| reports.borrow_mut().push(n)).count();
+    /// assert_eq!(count, 5);
+    /// assert_eq!(reports.into_inner(), vec![2, 4]);
+    /// ```
+    pub fn with_progress(self, interval: usize, cb: impl Fn(usize) + 'a) -> ProgressOrderedIterator<'a, T> {
+        assert!(interval > 0, "progress interval must be positive");
+        ProgressOrderedIterator { inner: self, interval, emitted: 0, cb: Box::new(cb) }
+    }
+
+    /// Take the error that ended a checked iterator, if any.
+    /// The iterator stays ended afterwards.
+    pub fn take_error(&mut self) -> Option<UnsortedInputError> {
+        self.error.take()
+    }
+
+    fn choose(&mut self)->Option<T> {
+        if self.head.is_empty() || self.failed {
+            self.finished = true;
+        }
+        if self.finished {
             return None;
         }
+        // Check selected index. If the index is not selected, return end of iterator
+        let chosen = self.head.pop()?;
         // Find the index
-        let chosen = chosen.unwrap();
         let chosen_index = chosen.iter_index;
         // Get the next item at the same location
-        let iter = self.iters.get_mut(chosen_index);
+        let iter = self.iters[chosen_index].as_mut();
         let iter_next = iter.unwrap().next();
-        if iter_next.is_some() {
-            let next_elem = iter_next.unwrap();
+        if iter_next.is_none() {
+            // Only heap items of live sources exist, so the slot is unused now
+            self.iters[chosen_index] = None;
+            self.free_slots.push(chosen_index);
+        }
+        if let Some(next_elem) = iter_next {
+            if self.checked && (self.comparator)(&next_elem, &chosen.what) == Ordering::Less {
+                // The chosen item is still in order, the next one is not
+                self.failed = true;
+                self.finished = true;
+                self.error = Some(UnsortedInputError { source_index: chosen.source_order });
+                return Some(chosen.what);
+            }
             self.head.push(HeapItem {
-                comparator: self.comparator,
+                comparator: self.comparator.clone(),
                 iter_index: chosen_index,
+                source_order: chosen.source_order,
+                stable: self.stable,
                 what: next_elem
             });
         }
-        return Some(chosen.what);
+        Some(chosen.what)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Error reported by a checked OrderedIterator when a source is not sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsortedInputError {
+    /// Position of the offending source in `add` order
+    pub source_index: usize,
+}
 
-    #[test]
-    fn seq_test() {
-        let v1 = vec![1,2,3,4,5];
-        let v2 = vec![6,7,8];
-        let v3 = vec![9,10];
-        let v4 = [1,2,4,5,6,7,8];
-        let mut seq_iter = SeqIter::new();
-        seq_iter.add(Box::new(v1.into_iter()));
-        seq_iter.add(Box::new(v2.into_iter()));
-        seq_iter.add(Box::new(v3.into_iter()));
-        seq_iter.add(Box::new(v4.into_iter()));
+impl std::fmt::Display for UnsortedInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source {} is not sorted", self.source_index)
+    }
+}
 
-        for i in seq_iter {
-            println!("{i}");
+impl std::error::Error for UnsortedInputError {}
+
+/// Iterator returned by `OrderedIterator::dedup`, `dedup_by_key` and `dedup_by`.
+pub struct DedupOrderedIterator<'a, T> {
+    inner: OrderedIterator<'a, T>,
+    same: Equivalence<'a, T>,
+}
+
+// Tells whether the second item is a duplicate of the first
+type Equivalence<'a, T> = Box<dyn Fn(&T, &T)->bool + 'a>;
+
+impl<'a, T> Iterator for DedupOrderedIterator<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let result = self.inner.next()?;
+        // Duplicates come next in a sorted merge, drop them now so no copy
+        // of the last value has to be kept.
+        while self.inner.peek().is_some_and(|x| (self.same)(&result, x)) {
+            self.inner.next();
         }
+        Some(result)
+    }
 
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.inner.size_hint();
+        (lower.min(1), upper)
     }
+}
 
+impl<'a, T> FusedIterator for DedupOrderedIterator<'a, T> {}
 
-    #[test]
-    fn test_multi_1() {
-        let v1: Vec<i32> = vec![1,2,3,4,5,11,19];
-        let v2: Vec<i32> = vec![1,7,8,12,44,231];
-        let v3: Vec<i32> = vec![3,5,7,9,10,1000];
-        let v4: [i32; 7] = [1,2,4,5,6,7,8];
+/// Iterator returned by `OrderedIterator::with_progress`.
+pub struct ProgressOrderedIterator<'a, T> {
+    inner: OrderedIterator<'a, T>,
+    interval: usize,
+    emitted: usize,
+    cb: Box<dyn Fn(usize) + 'a>,
+}
 
+impl<'a, T> Iterator for ProgressOrderedIterator<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let result = self.inner.next()?;
+        self.emitted += 1;
+        // is_multiple_of needs Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        if self.emitted % self.interval == 0 {
+            (self.cb)(self.emitted);
+        }
+        Some(result)
+    }
 
-        let choose_fn = |x:&Vec<i32>| -> Option<usize> {
-            let result = x.iter().enumerate().min_by(|x, y| {
-                x.1.cmp(&y.1)
-            }).map(|x| x.0);
-            println!("Choosing from {:?} -> {:?}", x, result);
-            return result;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for ProgressOrderedIterator<'a, T> {}
+
+/// Round robin iterator takes one item from each iterator in turn.
+/// Exhausted iterators are dropped and the others continue in the same order.
+/// Iterators may borrow, the RoundRobinIter then can't outlive the borrowed data.
+///
+/// Example
+///
+/// ```
+/// use iterutils::RoundRobinIter;
+/// let mut rr_iter = RoundRobinIter::new();
+/// rr_iter.add(Box::new(vec![1, 2, 3].into_iter()));
+/// rr_iter.add(Box::new(vec![10].into_iter()));
+/// rr_iter.add(Box::new(vec![100, 200].into_iter()));
+/// assert_eq!(rr_iter.collect::<Vec<_>>(), vec![1, 10, 100, 2, 200, 3]);
+/// ```
+pub struct RoundRobinIter<'a, T> {
+    ptr: usize,
+    iters: Vec<Box<dyn Iterator<Item = T> + 'a>>,
+    // Set by the first None, so iterators added afterwards are never consumed
+    finished: bool,
+}
+
+impl<'a, T> RoundRobinIter<'a, T> {
+    /// Create an empty RoundRobinIter.
+    pub fn new() -> RoundRobinIter<'a, T> {
+        RoundRobinIter { ptr: 0, iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed iterator at the end of the rotation.
+    ///
+    /// Once `next` returned None the RoundRobinIter is fused: the iterator
+    /// is dropped without being consumed.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>) {
+        if !self.finished {
+            self.iters.push(iter);
+        }
+    }
+
+    /// Number of iterators not known to be exhausted.
+    pub fn source_count(&self) -> usize {
+        self.iters.len()
+    }
+}
+
+impl<'a, T> Default for RoundRobinIter<'a, T> {
+    fn default() -> Self {
+        RoundRobinIter::new()
+    }
+}
+
+impl<'a, T> Iterator for RoundRobinIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        while !self.iters.is_empty() {
+            if self.ptr >= self.iters.len() {
+                self.ptr = 0;
+            }
+            match self.iters[self.ptr].next() {
+                Some(next) => {
+                    self.ptr += 1;
+                    return Some(next);
+                }
+                None => {
+                    // keep the rotation order, ptr now points to the next one
+                    let _ = self.iters.remove(self.ptr);
+                }
+            }
+        }
+        self.finished = true;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iters.iter()
+            .fold((0, Some(0)), |acc, iter| add_size_hints(acc, iter.size_hint()))
+    }
+}
+
+/// Exhausted iterators are dropped, so RoundRobinIter keeps returning None,
+/// even if new iterators are added.
+impl<'a, T> FusedIterator for RoundRobinIter<'a, T> {}
+
+/// Weighted round robin iterator takes up to `weight` items from each iterator
+/// in turn before moving on to the next one.
+/// Exhausted iterators are dropped and the others continue in the same order.
+///
+/// Example
+///
+/// ```
+/// use iterutils::WeightedRoundRobinIter;
+/// let mut wrr_iter = WeightedRoundRobinIter::new();
+/// wrr_iter.add(Box::new(vec![1, 2, 3, 4, 5].into_iter()), 2);
+/// wrr_iter.add(Box::new(vec![10, 20].into_iter()), 1);
+/// assert_eq!(wrr_iter.collect::<Vec<_>>(), vec![1, 2, 10, 3, 4, 20, 5]);
+/// ```
+pub struct WeightedRoundRobinIter<T> {
+    ptr: usize,
+    // items taken from the current iterator in this turn
+    taken: usize,
+    iters: Vec<(Box<dyn Iterator<Item = T>>, usize)>,
+    // Set by the first None, so iterators added afterwards are never consumed
+    finished: bool,
+}
+
+impl<T> WeightedRoundRobinIter<T> {
+    /// Create an empty WeightedRoundRobinIter.
+    pub fn new() -> WeightedRoundRobinIter<T> {
+        WeightedRoundRobinIter { ptr: 0, taken: 0, iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed iterator at the end of the rotation, emitting up to
+    /// `weight` items per turn.
+    /// An iterator with weight 0 would never be consumed and is dropped.
+    /// So is any iterator added after `next` returned None, since the
+    /// WeightedRoundRobinIter is fused.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T>>, weight: usize) {
+        if weight > 0 && !self.finished {
+            self.iters.push((iter, weight));
+        }
+    }
+
+    /// Number of iterators not known to be exhausted.
+    pub fn source_count(&self) -> usize {
+        self.iters.len()
+    }
+}
+
+impl<T> Default for WeightedRoundRobinIter<T> {
+    fn default() -> Self {
+        WeightedRoundRobinIter::new()
+    }
+}
+
+impl<T> Iterator for WeightedRoundRobinIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        while !self.iters.is_empty() {
+            if self.ptr >= self.iters.len() {
+                self.ptr = 0;
+            }
+            let (iter, weight) = &mut self.iters[self.ptr];
+            if self.taken >= *weight {
+                // turn is over
+                self.ptr += 1;
+                self.taken = 0;
+                continue;
+            }
+            match iter.next() {
+                Some(next) => {
+                    self.taken += 1;
+                    return Some(next);
+                }
+                None => {
+                    // keep the rotation order, ptr now points to the next one
+                    let _ = self.iters.remove(self.ptr);
+                    self.taken = 0;
+                }
+            }
+        }
+        self.finished = true;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iters.iter()
+            .fold((0, Some(0)), |acc, (iter, _)| add_size_hints(acc, iter.size_hint()))
+    }
+}
+
+/// Exhausted iterators are dropped, so WeightedRoundRobinIter keeps returning
+/// None, even if new iterators are added.
+impl<T> FusedIterator for WeightedRoundRobinIter<T> {}
+
+/// Zip iterator advances all iterators together and yields their items as a
+/// `Vec`, in the order the iterators were added.
+/// It ends as soon as any iterator is exhausted, like `Iterator::zip`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ZipMultiIter;
+/// let mut zip_iter = ZipMultiIter::new();
+/// zip_iter.add(Box::new(vec![1, 2, 3].into_iter()));
+/// zip_iter.add(Box::new(vec![10, 20].into_iter()));
+/// zip_iter.add(Box::new(vec![100, 200, 300].into_iter()));
+/// assert_eq!(zip_iter.collect::<Vec<_>>(), vec![vec![1, 10, 100], vec![2, 20, 200]]);
+/// ```
+pub struct ZipMultiIter<T> {
+    iters: Vec<Box<dyn Iterator<Item = T>>>,
+    finished: bool,
+}
+
+impl<T> ZipMultiIter<T> {
+    /// Create an empty ZipMultiIter. Without iterators it yields nothing.
+    pub fn new() -> ZipMultiIter<T> {
+        ZipMultiIter { iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed iterator. Its items are appended to each yielded `Vec`.
+    /// Iterators added after `next` returned None are never consumed.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T>>) {
+        self.iters.push(iter);
+    }
+}
+
+impl<T> Default for ZipMultiIter<T> {
+    fn default() -> Self {
+        ZipMultiIter::new()
+    }
+}
+
+impl<T> Iterator for ZipMultiIter<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.iters.is_empty() {
+            // Iterators added later must not revive it
+            self.finished = true;
+        }
+        if self.finished {
+            return None;
+        }
+        let mut result = Vec::with_capacity(self.iters.len());
+        for iter in self.iters.iter_mut() {
+            match iter.next() {
+                Some(next) => result.push(next),
+                None => {
+                    // Other iterators may still have items, never poll again
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished || self.iters.is_empty() {
+            return (0, Some(0));
+        }
+        self.iters.iter().map(|x| x.size_hint()).fold((usize::MAX, None), |(l1, u1), (l2, u2)| {
+            let upper = match (u1, u2) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            (l1.min(l2), upper)
+        })
+    }
+}
+
+/// Once any iterator is exhausted ZipMultiIter keeps returning None.
+impl<T> FusedIterator for ZipMultiIter<T> {}
+
+/// Zip iterator that runs until all iterators are exhausted.
+/// Each yielded `Vec` has one entry per iterator, in the order they were added,
+/// with `None` in place of iterators that are already exhausted.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ZipLongestMultiIter;
+/// let mut zip_iter = ZipLongestMultiIter::new();
+/// zip_iter.add(Box::new(vec![1, 2].into_iter()));
+/// zip_iter.add(Box::new(vec![10].into_iter()));
+/// assert_eq!(zip_iter.collect::<Vec<_>>(), vec![vec![Some(1), Some(10)], vec![Some(2), None]]);
+/// ```
+pub struct ZipLongestMultiIter<T> {
+    iters: Vec<Option<Box<dyn Iterator<Item = T>>>>,
+    // Set by the first None, so iterators added afterwards are never consumed
+    finished: bool,
+}
+
+impl<T> ZipLongestMultiIter<T> {
+    /// Create an empty ZipLongestMultiIter. Without iterators it yields nothing.
+    pub fn new() -> ZipLongestMultiIter<T> {
+        ZipLongestMultiIter { iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed iterator. Its items are appended to each yielded `Vec`.
+    ///
+    /// Once `next` returned None the ZipLongestMultiIter is fused: the
+    /// iterator is dropped without being consumed.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T>>) {
+        if !self.finished {
+            self.iters.push(Some(iter));
+        }
+    }
+}
+
+impl<T> Default for ZipLongestMultiIter<T> {
+    fn default() -> Self {
+        ZipLongestMultiIter::new()
+    }
+}
+
+impl<T> Iterator for ZipLongestMultiIter<T> {
+    type Item = Vec<Option<T>>;
+    fn next(&mut self) -> Option<Vec<Option<T>>> {
+        if self.finished {
+            return None;
+        }
+        let mut result = Vec::with_capacity(self.iters.len());
+        let mut any = false;
+        for slot in self.iters.iter_mut() {
+            // Exhausted iterators are dropped so they are never polled again
+            let next = slot.as_mut().and_then(|iter| iter.next());
+            if next.is_none() {
+                *slot = None;
+            } else {
+                any = true;
+            }
+            result.push(next);
+        }
+        if any {
+            Some(result)
+        } else {
+            self.finished = true;
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iters.iter().flatten().map(|x| x.size_hint()).fold((0, Some(0)), |(l1, u1), (l2, u2)| {
+            let upper = match (u1, u2) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            };
+            (l1.max(l2), upper)
+        })
+    }
+}
+
+/// Once all iterators are exhausted ZipLongestMultiIter keeps returning None,
+/// even if new iterators are added.
+impl<T> FusedIterator for ZipLongestMultiIter<T> {}
+
+struct TeeShared<T, I> {
+    source: Option<I>,
+    buffers: Vec<VecDeque<T>>,
+    alive: Vec<bool>,
+}
+
+/// One of the iterators returned by [`tee`].
+/// Every handle yields all items of the source, independently of the others.
+/// Items not yet consumed by a handle are buffered for it,
+/// so a handle that lags far behind costs memory.
+/// Dropping a handle frees its buffer.
+pub struct TeeHandle<T, I> {
+    index: usize,
+    shared: Arc<Mutex<TeeShared<T, I>>>,
+}
+
+/// Split one iterator into `n` independent iterators.
+/// The source is advanced only when a handle runs out of buffered items.
+/// Handles are `Send` when `T` and the source are `Send`, so they can be consumed from different threads.
+/// The source may borrow, e.g. `v.iter().cloned()`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::tee;
+/// let mut handles = tee(vec![1, 2, 3].into_iter(), 2);
+/// let second = handles.pop().unwrap();
+/// let first = handles.pop().unwrap();
+/// assert_eq!(first.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// assert_eq!(second.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+pub fn tee<T, I>(iter: I, n: usize) -> Vec<TeeHandle<T, I>>
+where
+    T: Clone,
+    I: Iterator<Item = T>,
+{
+    let shared = TeeShared {
+        source: Some(iter),
+        buffers: (0..n).map(|_| VecDeque::new()).collect(),
+        alive: vec![true; n],
+    };
+    let shared = Arc::new(Mutex::new(shared));
+    (0..n).map(|index| TeeHandle { index, shared: shared.clone() }).collect()
+}
+
+impl<T: Clone, I: Iterator<Item = T>> Iterator for TeeHandle<T, I> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(next) = shared.buffers[self.index].pop_front() {
+            return Some(next);
+        }
+        let next = shared.source.as_mut()?.next();
+        match next {
+            Some(next) => {
+                let TeeShared { buffers, alive, .. } = &mut *shared;
+                for (i, buffer) in buffers.iter_mut().enumerate() {
+                    if i != self.index && alive[i] {
+                        buffer.push_back(next.clone());
+                    }
+                }
+                Some(next)
+            },
+            None => {
+                shared.source = None;
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let shared = self.shared.lock().unwrap();
+        let buffered = shared.buffers[self.index].len();
+        let source = shared.source.as_ref().map_or((0, Some(0)), |x| x.size_hint());
+        add_size_hints((buffered, Some(buffered)), source)
+    }
+}
+
+/// Once the source is exhausted and the buffer is drained, TeeHandle keeps returning None.
+impl<T: Clone, I: Iterator<Item = T>> FusedIterator for TeeHandle<T, I> {}
+
+impl<T, I> Drop for TeeHandle<T, I> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.alive[self.index] = false;
+            shared.buffers[self.index].clear();
+        }
+    }
+}
+
+/// Interleave iterator alternates between two iterators: a0, b0, a1, b1, ...
+/// When one iterator is exhausted, the rest of the other one is yielded.
+/// For more than two iterators use `RoundRobinIter`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::InterleavedIter;
+/// let interleaved = InterleavedIter::new(Box::new(vec![1, 2, 3, 4].into_iter()), Box::new(vec![10, 20].into_iter()));
+/// assert_eq!(interleaved.collect::<Vec<_>>(), vec![1, 10, 2, 20, 3, 4]);
+/// ```
+pub struct InterleavedIter<T> {
+    a: Fuse<Box<dyn Iterator<Item = T>>>,
+    b: Fuse<Box<dyn Iterator<Item = T>>>,
+    toggle: bool,
+}
+
+impl<T> InterleavedIter<T> {
+    /// Create an InterleavedIter that starts with `a`.
+    pub fn new(a: Box<dyn Iterator<Item=T>>, b: Box<dyn Iterator<Item=T>>) -> InterleavedIter<T> {
+        InterleavedIter { a: a.fuse(), b: b.fuse(), toggle: false }
+    }
+}
+
+impl<T> Iterator for InterleavedIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let (first, second) = if self.toggle {
+            (&mut self.b, &mut self.a)
+        } else {
+            (&mut self.a, &mut self.b)
+        };
+        match first.next() {
+            Some(next) => {
+                self.toggle = !self.toggle;
+                Some(next)
+            },
+            None => second.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        add_size_hints(self.a.size_hint(), self.b.size_hint())
+    }
+}
+
+/// Both iterators are fused, so InterleavedIter keeps returning None once they are exhausted.
+impl<T> FusedIterator for InterleavedIter<T> {}
+
+/// Merge join iterator joins two iterators sorted ascending by key, like an SQL inner join.
+/// It yields a `(left, right)` pair for every combination of items with equal keys,
+/// items without a match on the other side are skipped.
+/// Right items with the same key are buffered, so both `L` and `R` must be `Clone`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::MergeJoinIter;
+/// let left = vec![(1, "a"), (2, "b"), (3, "c")];
+/// let right = vec![(2, 20), (3, 30), (3, 31), (4, 40)];
+/// let joined = MergeJoinIter::new(Box::new(left.into_iter()), Box::new(right.into_iter()), |x| x.0, |x| x.0);
+/// assert_eq!(joined.map(|(l, r)| (l.1, r.1)).collect::<Vec<_>>(), vec![("b", 20), ("c", 30), ("c", 31)]);
+/// ```
+pub struct MergeJoinIter<L, R, K> {
+    left: Fuse<Box<dyn Iterator<Item = L>>>,
+    right: Fuse<Box<dyn Iterator<Item = R>>>,
+    left_key: fn(&L) -> K,
+    right_key: fn(&R) -> K,
+    current_left: Option<L>,
+    group: Vec<R>,
+    group_key: Option<K>,
+    group_pos: usize,
+    pending_right: Option<R>,
+    right_done: bool,
+}
+
+impl<L, R, K> MergeJoinIter<L, R, K> {
+    /// Create a MergeJoinIter. Both iterators must be sorted ascending by their key.
+    pub fn new(left: Box<dyn Iterator<Item=L>>, right: Box<dyn Iterator<Item=R>>,
+        left_key: fn(&L) -> K, right_key: fn(&R) -> K) -> MergeJoinIter<L, R, K> {
+        MergeJoinIter {
+            left: left.fuse(),
+            right: right.fuse(),
+            left_key, right_key,
+            current_left: None,
+            group: Vec::new(),
+            group_key: None,
+            group_pos: 0,
+            pending_right: None,
+            right_done: false,
+        }
+    }
+}
+
+impl<L, R, K: Ord> MergeJoinIter<L, R, K> {
+    // Collect all right items with the given key, dropping smaller ones
+    fn fill_group(&mut self, key: K) {
+        self.group.clear();
+        loop {
+            let next = match self.pending_right.take() {
+                Some(next) => next,
+                None => match self.right.next() {
+                    Some(next) => next,
+                    None => {
+                        self.right_done = true;
+                        break;
+                    }
+                }
+            };
+            match (self.right_key)(&next).cmp(&key) {
+                Ordering::Less => {},
+                Ordering::Equal => self.group.push(next),
+                Ordering::Greater => {
+                    self.pending_right = Some(next);
+                    break;
+                }
+            }
+        }
+        self.group_key = Some(key);
+    }
+}
+
+impl<L: Clone, R: Clone, K: Ord> Iterator for MergeJoinIter<L, R, K> {
+    type Item = (L, R);
+    fn next(&mut self) -> Option<(L, R)> {
+        loop {
+            if let Some(left) = &self.current_left {
+                if self.group_pos < self.group.len() {
+                    let right = self.group[self.group_pos].clone();
+                    self.group_pos += 1;
+                    return Some((left.clone(), right));
+                }
+                self.current_left = None;
+            }
+            if self.right_done && self.pending_right.is_none() && self.group.is_empty() {
+                // Nothing left to match against
+                return None;
+            }
+            let left = self.left.next()?;
+            let key = (self.left_key)(&left);
+            match &self.group_key {
+                Some(group_key) if *group_key == key => {},
+                Some(group_key) if *group_key > key => continue,
+                _ => self.fill_group(key),
+            }
+            self.current_left = Some(left);
+            self.group_pos = 0;
+        }
+    }
+}
+
+/// Once either side is exhausted MergeJoinIter keeps returning None.
+impl<L: Clone, R: Clone, K: Ord> FusedIterator for MergeJoinIter<L, R, K> {}
+
+/// Left join iterator joins two iterators sorted ascending by key, like an SQL left join.
+/// It yields exactly one `(left, Some(right))` or `(left, None)` per left item,
+/// which makes it a streaming lookup against a sorted reference iterator.
+/// Right items with keys smaller than the current left key are skipped.
+/// A matching right item is kept for following left items with the same key,
+/// so if several right items share a key only the first one is used.
+///
+/// Example
+///
+/// ```
+/// use iterutils::LeftJoinIter;
+/// let left = vec![1, 2, 2, 4];
+/// let right = vec![(2, "two"), (3, "three"), (4, "four")];
+/// let joined = LeftJoinIter::new(Box::new(left.into_iter()), Box::new(right.into_iter()), |x| *x, |x| x.0);
+/// assert_eq!(joined.map(|(l, r)| (l, r.map(|x| x.1))).collect::<Vec<_>>(),
+///     vec![(1, None), (2, Some("two")), (2, Some("two")), (4, Some("four"))]);
+/// ```
+pub struct LeftJoinIter<L, R, K> {
+    left: Fuse<Box<dyn Iterator<Item = L>>>,
+    right: Fuse<Box<dyn Iterator<Item = R>>>,
+    left_key: fn(&L) -> K,
+    right_key: fn(&R) -> K,
+    pending_right: Option<R>,
+}
+
+impl<L, R, K> LeftJoinIter<L, R, K> {
+    /// Create a LeftJoinIter. Both iterators must be sorted ascending by their key.
+    pub fn new(left: Box<dyn Iterator<Item=L>>, right: Box<dyn Iterator<Item=R>>,
+        left_key: fn(&L) -> K, right_key: fn(&R) -> K) -> LeftJoinIter<L, R, K> {
+        LeftJoinIter {
+            left: left.fuse(),
+            right: right.fuse(),
+            left_key, right_key,
+            pending_right: None,
+        }
+    }
+}
+
+impl<L, R: Clone, K: Ord> Iterator for LeftJoinIter<L, R, K> {
+    type Item = (L, Option<R>);
+    fn next(&mut self) -> Option<(L, Option<R>)> {
+        let left = self.left.next()?;
+        let key = (self.left_key)(&left);
+        loop {
+            if self.pending_right.is_none() {
+                self.pending_right = self.right.next();
+            }
+            let right = match &self.pending_right {
+                Some(right) => right,
+                None => return Some((left, None)),
+            };
+            match (self.right_key)(right).cmp(&key) {
+                Ordering::Less => self.pending_right = None,
+                Ordering::Equal => return Some((left, Some(right.clone()))),
+                Ordering::Greater => return Some((left, None)),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.left.size_hint()
+    }
+}
+
+/// Both iterators are fused, so LeftJoinIter keeps returning None once the left side is exhausted.
+impl<L, R: Clone, K: Ord> FusedIterator for LeftJoinIter<L, R, K> {}
+
+/// Union of iterators sorted ascending. Every distinct value is emitted once,
+/// even if several iterators (or one iterator several times) contain it.
+/// Same as `OrderedIterator::new_min()` followed by `.dedup()`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::UnionSortedIter;
+/// let mut union = UnionSortedIter::new();
+/// union.add(Box::new(vec![1, 3, 5].into_iter()));
+/// union.add(Box::new(vec![1, 2, 3].into_iter()));
+/// assert_eq!(union.collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+/// ```
+pub struct UnionSortedIter<'a, T> {
+    inner: DedupOrderedIterator<'a, T>,
+}
+
+impl<'a, T: Ord + 'a> UnionSortedIter<'a, T> {
+    /// Create an empty UnionSortedIter.
+    pub fn new() -> UnionSortedIter<'a, T> {
+        UnionSortedIter { inner: OrderedIterator::new_min().dedup() }
+    }
+
+    /// Add a Boxed iterator. It must be sorted ascending.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T> + 'a>) {
+        self.inner.inner.add(iter);
+    }
+}
+
+impl<'a, T: Ord + 'a> Default for UnionSortedIter<'a, T> {
+    fn default() -> Self {
+        UnionSortedIter::new()
+    }
+}
+
+impl<'a, T> Iterator for UnionSortedIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Once all iterators are exhausted UnionSortedIter keeps returning None,
+/// even if new iterators are added.
+impl<'a, T> FusedIterator for UnionSortedIter<'a, T> {}
+
+/// Intersection of iterators sorted ascending. A value is emitted only when
+/// every iterator contains it. Repeated values are emitted as many times as
+/// the iterator with the fewest repetitions contains them.
+/// Without iterators nothing is emitted.
+///
+/// Example
+///
+/// ```
+/// use iterutils::IntersectionSortedIter;
+/// let mut intersection = IntersectionSortedIter::new();
+/// intersection.add(Box::new(vec![1, 2, 3, 5].into_iter()));
+/// intersection.add(Box::new(vec![2, 3, 4, 5].into_iter()));
+/// intersection.add(Box::new(vec![0, 3, 5].into_iter()));
+/// assert_eq!(intersection.collect::<Vec<_>>(), vec![3, 5]);
+/// ```
+pub struct IntersectionSortedIter<T> {
+    iters: Vec<Box<dyn Iterator<Item = T>>>,
+    finished: bool,
+}
+
+impl<T: Ord> IntersectionSortedIter<T> {
+    /// Create an empty IntersectionSortedIter.
+    pub fn new() -> IntersectionSortedIter<T> {
+        IntersectionSortedIter { iters: Vec::new(), finished: false }
+    }
+
+    /// Add a Boxed iterator. It must be sorted ascending.
+    /// Iterators added after `next` returned None are never consumed.
+    pub fn add(&mut self, iter: Box<dyn Iterator<Item=T>>) {
+        self.iters.push(iter);
+    }
+}
+
+impl<T: Ord> Default for IntersectionSortedIter<T> {
+    fn default() -> Self {
+        IntersectionSortedIter::new()
+    }
+}
+
+impl<T: Ord> Iterator for IntersectionSortedIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.iters.is_empty() {
+            // Iterators added later must not revive it
+            self.finished = true;
+        }
+        if self.finished {
+            return None;
+        }
+        let mut heads = Vec::with_capacity(self.iters.len());
+        for iter in self.iters.iter_mut() {
+            match iter.next() {
+                Some(next) => heads.push(next),
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            }
+        }
+        loop {
+            let mut max_index = 0;
+            for i in 1..heads.len() {
+                if heads[i] > heads[max_index] {
+                    max_index = i;
+                }
+            }
+            // Advance every head to the largest one. A head may pass it,
+            // then another round with the new largest head is needed.
+            let mut converged = true;
+            for i in 0..heads.len() {
+                while heads[i] < heads[max_index] {
+                    match self.iters[i].next() {
+                        Some(next) => heads[i] = next,
+                        None => {
+                            self.finished = true;
+                            return None;
+                        }
+                    }
+                }
+                if heads[i] != heads[max_index] {
+                    converged = false;
+                }
+            }
+            if converged {
+                return Some(heads.swap_remove(0));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished || self.iters.is_empty() {
+            return (0, Some(0));
+        }
+        let upper = self.iters.iter().filter_map(|x| x.size_hint().1).min();
+        (0, upper)
+    }
+}
+
+/// Once any iterator is exhausted IntersectionSortedIter keeps returning None.
+impl<T: Ord> FusedIterator for IntersectionSortedIter<T> {}
+
+/// Difference of two iterators sorted ascending: the items of `left`
+/// whose value does not appear in `right`.
+/// All left items equal to a right item are skipped, not just one per right item.
+///
+/// Example
+///
+/// ```
+/// use iterutils::DifferenceSortedIter;
+/// let difference = DifferenceSortedIter::new(Box::new(vec![1, 2, 2, 3, 5].into_iter()), Box::new(vec![2, 4, 5].into_iter()));
+/// assert_eq!(difference.collect::<Vec<_>>(), vec![1, 3]);
+/// ```
+pub struct DifferenceSortedIter<T> {
+    left: Fuse<Box<dyn Iterator<Item = T>>>,
+    right: Peekable<Fuse<Box<dyn Iterator<Item = T>>>>,
+}
+
+impl<T: Ord> DifferenceSortedIter<T> {
+    /// Create a DifferenceSortedIter. Both iterators must be sorted ascending.
+    pub fn new(left: Box<dyn Iterator<Item=T>>, right: Box<dyn Iterator<Item=T>>) -> DifferenceSortedIter<T> {
+        DifferenceSortedIter { left: left.fuse(), right: right.fuse().peekable() }
+    }
+}
+
+impl<T: Ord> Iterator for DifferenceSortedIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let next = self.left.next()?;
+            while self.right.next_if(|x| *x < next).is_some() {}
+            // The right head is kept, following left items may be equal too
+            if self.right.peek() != Some(&next) {
+                return Some(next);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.left.size_hint().1)
+    }
+}
+
+/// Both iterators are fused, so DifferenceSortedIter keeps returning None once `left` is exhausted.
+impl<T: Ord> FusedIterator for DifferenceSortedIter<T> {}
+
+/// Symmetric difference of two iterators sorted ascending: the items that
+/// appear in one iterator but not in the other.
+/// Equal heads cancel out pairwise, so a value repeated more often in one
+/// iterator is emitted for the extra repetitions.
+///
+/// Example
+///
+/// ```
+/// use iterutils::SymmetricDifferenceSortedIter;
+/// let sym_diff = SymmetricDifferenceSortedIter::new(Box::new(vec![1, 2, 3].into_iter()), Box::new(vec![2, 4].into_iter()));
+/// assert_eq!(sym_diff.collect::<Vec<_>>(), vec![1, 3, 4]);
+/// ```
+pub struct SymmetricDifferenceSortedIter<T> {
+    a: Peekable<Fuse<Box<dyn Iterator<Item = T>>>>,
+    b: Peekable<Fuse<Box<dyn Iterator<Item = T>>>>,
+}
+
+impl<T: Ord> SymmetricDifferenceSortedIter<T> {
+    /// Create a SymmetricDifferenceSortedIter. Both iterators must be sorted ascending.
+    pub fn new(a: Box<dyn Iterator<Item=T>>, b: Box<dyn Iterator<Item=T>>) -> SymmetricDifferenceSortedIter<T> {
+        SymmetricDifferenceSortedIter { a: a.fuse().peekable(), b: b.fuse().peekable() }
+    }
+}
+
+impl<T: Ord> Iterator for SymmetricDifferenceSortedIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let order = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+            match order {
+                Ordering::Less => return self.a.next(),
+                Ordering::Greater => return self.b.next(),
+                Ordering::Equal => {
+                    self.a.next();
+                    self.b.next();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = add_size_hints(self.a.size_hint(), self.b.size_hint());
+        (0, upper)
+    }
+}
+
+/// Both iterators are fused, so SymmetricDifferenceSortedIter keeps returning None once they are exhausted.
+impl<T: Ord> FusedIterator for SymmetricDifferenceSortedIter<T> {}
+
+/// Group iterator collects consecutive items with the same key into a `Vec`
+/// and yields it together with the key.
+/// Only consecutive items are grouped, so after an `OrderedIterator` sorted
+/// by the same key every key is yielded exactly once.
+///
+/// Example
+///
+/// ```
+/// use iterutils::{GroupByIter, OrderedIterator};
+/// let mut o_iter = OrderedIterator::new_by_key(|x: &(u32, char)| x.0);
+/// o_iter.add(Box::new(vec![(1, 'a'), (2, 'a')].into_iter()));
+/// o_iter.add(Box::new(vec![(1, 'b'), (3, 'b')].into_iter()));
+/// let groups = GroupByIter::new(Box::new(o_iter), |x| x.0);
+/// assert_eq!(groups.collect::<Vec<_>>(), vec![
+///     (1, vec![(1, 'a'), (1, 'b')]),
+///     (2, vec![(2, 'a')]),
+///     (3, vec![(3, 'b')]),
+/// ]);
+/// ```
+pub struct GroupByIter<T, K> {
+    iter: Fuse<Box<dyn Iterator<Item = T>>>,
+    key: fn(&T) -> K,
+    pending: Option<T>,
+    max_len: usize,
+}
+
+impl<T, K: PartialEq> GroupByIter<T, K> {
+    /// Create a GroupByIter grouping the items of `iter` by `key`.
+    pub fn new(iter: Box<dyn Iterator<Item=T>>, key: fn(&T) -> K) -> GroupByIter<T, K> {
+        GroupByIter { iter: iter.fuse(), key, pending: None, max_len: usize::MAX }
+    }
+}
+
+impl<T, K: PartialEq> Iterator for GroupByIter<T, K> {
+    type Item = (K, Vec<T>);
+    fn next(&mut self) -> Option<(K, Vec<T>)> {
+        let first = self.pending.take().or_else(|| self.iter.next())?;
+        let key = (self.key)(&first);
+        let mut group = vec![first];
+        while group.len() < self.max_len {
+            let next = match self.iter.next() {
+                Some(next) => next,
+                None => break,
+            };
+            if (self.key)(&next) == key {
+                group.push(next);
+            } else {
+                // First item of the next group
+                self.pending = Some(next);
+                break;
+            }
+        }
+        Some((key, group))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = self.pending.is_some() as usize;
+        let (lower, upper) = add_size_hints((pending, Some(pending)), self.iter.size_hint());
+        (lower.min(1), upper)
+    }
+}
+
+/// The wrapped iterator is fused, so GroupByIter keeps returning None once it is exhausted.
+impl<T, K: PartialEq> FusedIterator for GroupByIter<T, K> {}
+
+/// Batch iterator yields consecutive items with the same key together, like `GroupByIter`,
+/// but accepts any iterator and can limit the size of a batch.
+/// A run of equal keys longer than the limit is split into several batches with the same key.
+///
+/// Example
+///
+/// ```
+/// use iterutils::BatchByIter;
+/// let batches = BatchByIter::new(vec![1, 1, 1, 2].into_iter(), |x| *x).max_batch_size(2);
+/// assert_eq!(batches.collect::<Vec<_>>(), vec![(1, vec![1, 1]), (1, vec![1]), (2, vec![2])]);
+/// ```
+pub struct BatchByIter<T, K> {
+    inner: GroupByIter<T, K>,
+}
+
+impl<T, K: PartialEq> BatchByIter<T, K> {
+    /// Create a BatchByIter batching the items of `iter` by `key`, without size limit.
+    pub fn new(iter: impl Iterator<Item=T> + 'static, key: fn(&T) -> K) -> BatchByIter<T, K> {
+        BatchByIter { inner: GroupByIter::new(Box::new(iter), key) }
+    }
+
+    /// Limit batches to at most `max` items.
+    ///
+    /// Panics if `max` is 0.
+    pub fn max_batch_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "max batch size must not be 0");
+        self.inner.max_len = max;
+        self
+    }
+}
+
+impl<T, K: PartialEq> Iterator for BatchByIter<T, K> {
+    type Item = (K, Vec<T>);
+    fn next(&mut self) -> Option<(K, Vec<T>)> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, K: PartialEq> FusedIterator for BatchByIter<T, K> {}
+
+/// Chunk iterator collects `chunk_size` items at a time into a `Vec`.
+/// The last chunk holds the remaining items and may be smaller.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ChunkedIter;
+/// let chunks = ChunkedIter::new(Box::new(1..=5), 2);
+/// assert_eq!(chunks.collect::<Vec<_>>(), vec![vec![1, 2], vec![3, 4], vec![5]]);
+/// ```
+pub struct ChunkedIter<T> {
+    iter: Fuse<Box<dyn Iterator<Item = T>>>,
+    chunk_size: usize,
+}
+
+impl<T> ChunkedIter<T> {
+    /// Create a ChunkedIter yielding chunks of `chunk_size` items.
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn new(iter: Box<dyn Iterator<Item=T>>, chunk_size: usize) -> ChunkedIter<T> {
+        assert!(chunk_size > 0, "chunk_size must not be 0");
+        ChunkedIter { iter: iter.fuse(), chunk_size }
+    }
+}
+
+impl<T> Iterator for ChunkedIter<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Vec<T>> {
+        let first = self.iter.next()?;
+        let mut chunk = Vec::with_capacity(self.chunk_size);
+        chunk.push(first);
+        chunk.extend(self.iter.by_ref().take(self.chunk_size - 1));
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        (lower.div_ceil(self.chunk_size), upper.map(|x| x.div_ceil(self.chunk_size)))
+    }
+}
+
+/// The wrapped iterator is fused, so ChunkedIter keeps returning None once it is exhausted.
+impl<T> FusedIterator for ChunkedIter<T> {}
+
+/// Sliding window iterator yields every run of `window_size` consecutive items,
+/// moving forward one item at a time. Handy for computing deltas between neighbours.
+/// If there are fewer than `window_size` items nothing is yielded.
+///
+/// Example
+///
+/// ```
+/// use iterutils::WindowIter;
+/// let windows = WindowIter::new(Box::new(vec![1, 3, 6, 10].into_iter()), 2);
+/// assert_eq!(windows.map(|x| x[1] - x[0]).collect::<Vec<_>>(), vec![2, 3, 4]);
+/// ```
+pub struct WindowIter<T> {
+    iter: Fuse<Box<dyn Iterator<Item = T>>>,
+    window_size: usize,
+    window: VecDeque<T>,
+}
+
+impl<T: Clone> WindowIter<T> {
+    /// Create a WindowIter yielding windows of `window_size` items.
+    ///
+    /// Panics if `window_size` is 0.
+    pub fn new(iter: Box<dyn Iterator<Item=T>>, window_size: usize) -> WindowIter<T> {
+        assert!(window_size > 0, "window_size must not be 0");
+        WindowIter { iter: iter.fuse(), window_size, window: VecDeque::with_capacity(window_size) }
+    }
+}
+
+impl<T: Clone> Iterator for WindowIter<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.window.len() == self.window_size {
+            self.window.pop_front();
+        }
+        while self.window.len() < self.window_size {
+            self.window.push_back(self.iter.next()?);
+        }
+        Some(self.window.iter().cloned().collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.window.len();
+        // A full window has been yielded already, every further item makes a new one
+        let windows = |remaining: usize| if buffered == self.window_size {
+            remaining
+        } else {
+            remaining.saturating_add(buffered).saturating_sub(self.window_size - 1)
+        };
+        let (lower, upper) = self.iter.size_hint();
+        (windows(lower), upper.map(windows))
+    }
+}
+
+/// The wrapped iterator is fused, so WindowIter keeps returning None once it is exhausted.
+impl<T: Clone> FusedIterator for WindowIter<T> {}
+
+/// Cartesian product iterator yields every combination of one item from each iterator,
+/// in lexicographic order: the first iterator changes slowest.
+/// The iterators are collected when the CartesianProductIter is created.
+/// Without iterators, or if any iterator is empty, nothing is yielded.
+///
+/// Example
+///
+/// ```
+/// use iterutils::CartesianProductIter;
+/// let product = CartesianProductIter::new(vec![
+///     Box::new(vec![1, 2].into_iter()) as Box<dyn Iterator<Item = i32>>,
+///     Box::new(vec![10, 20].into_iter()),
+/// ]);
+/// assert_eq!(product.collect::<Vec<_>>(), vec![vec![1, 10], vec![1, 20], vec![2, 10], vec![2, 20]]);
+/// ```
+pub struct CartesianProductIter<T> {
+    pools: Vec<Vec<T>>,
+    indices: Vec<usize>,
+    finished: bool,
+}
+
+impl<T: Clone> CartesianProductIter<T> {
+    /// Create a CartesianProductIter over the given iterators.
+    pub fn new(iters: impl IntoIterator<Item=Box<dyn Iterator<Item=T>>>) -> CartesianProductIter<T> {
+        let pools: Vec<Vec<T>> = iters.into_iter().map(|x| x.collect()).collect();
+        let finished = pools.is_empty() || pools.iter().any(|x| x.is_empty());
+        let indices = vec![0; pools.len()];
+        CartesianProductIter { pools, indices, finished }
+    }
+}
+
+impl<T: Clone> Iterator for CartesianProductIter<T> {
+    type Item = Vec<T>;
+    fn next(&mut self) -> Option<Vec<T>> {
+        if self.finished {
+            return None;
+        }
+        let result = self.indices.iter().zip(self.pools.iter()).map(|(i, pool)| pool[*i].clone()).collect();
+        // Advance like an odometer, the last position moves first
+        self.finished = true;
+        for (index, pool) in self.indices.iter_mut().zip(self.pools.iter()).rev() {
+            *index += 1;
+            if *index < pool.len() {
+                self.finished = false;
+                break;
+            }
+            *index = 0;
+        }
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            return (0, Some(0));
+        }
+        // Remaining = total combinations - combinations already yielded
+        let mut total: Option<usize> = Some(1);
+        let mut done: Option<usize> = Some(0);
+        for (index, pool) in self.indices.iter().zip(self.pools.iter()) {
+            total = total.and_then(|x| x.checked_mul(pool.len()));
+            done = done.and_then(|x| x.checked_mul(pool.len())).and_then(|x| x.checked_add(*index));
+        }
+        match (total, done) {
+            (Some(total), Some(done)) => (total - done, Some(total - done)),
+            // Too many to count, but the current combination is still left
+            _ => (1, None),
+        }
+    }
+}
+
+impl<T: Clone> FusedIterator for CartesianProductIter<T> {}
+
+/// Priority queue iterator yields the largest pushed item first.
+/// Unlike `OrderedIterator` it has no source iterators, items are pushed one by one,
+/// also while iterating. Useful for event driven simulations.
+/// Items pushed after `next` returned None are still emitted, so it is not a `FusedIterator`.
+///
+/// Example
+///
+/// ```
+/// use iterutils::PriorityQueueIter;
+/// let mut pq_iter = PriorityQueueIter::new();
+/// pq_iter.push(2);
+/// pq_iter.push(5);
+/// assert_eq!(pq_iter.next(), Some(5));
+/// pq_iter.push(3);
+/// assert_eq!(pq_iter.collect::<Vec<_>>(), vec![3, 2]);
+/// ```
+pub struct PriorityQueueIter<T> {
+    heap: BinaryHeap<T>,
+}
+
+impl<T: Ord> PriorityQueueIter<T> {
+    /// Create an empty PriorityQueueIter.
+    pub fn new() -> PriorityQueueIter<T> {
+        PriorityQueueIter { heap: BinaryHeap::new() }
+    }
+
+    /// Push an item.
+    pub fn push(&mut self, item: T) {
+        self.heap.push(item);
+    }
+
+    /// The item yielded next, without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    /// Number of queued items.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Tell if no item is queued.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl<T: Ord> Default for PriorityQueueIter<T> {
+    fn default() -> Self {
+        PriorityQueueIter::new()
+    }
+}
+
+impl<T: Ord> Iterator for PriorityQueueIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.heap.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl<T: Ord> ExactSizeIterator for PriorityQueueIter<T> {}
+
+struct PartitionShared<T> {
+    source: Fuse<Box<dyn Iterator<Item = T>>>,
+    pred: Box<dyn Fn(&T) -> bool>,
+    // Index 0 buffers matching items, index 1 the others
+    buffers: [VecDeque<T>; 2],
+    alive: [bool; 2],
+}
+
+/// One of the two iterators returned by [`partition_lazy`].
+pub struct PartitionHandle<T> {
+    side: usize,
+    shared: Rc<RefCell<PartitionShared<T>>>,
+}
+
+/// Split an iterator into the items matching `pred` and the others, lazily.
+/// Returns `(matching, not_matching)`. When one side is polled, items for the
+/// other side are buffered until it is polled too, or dropped if it was dropped.
+///
+/// Example
+///
+/// ```
+/// use iterutils::partition_lazy;
+/// let (even, odd) = partition_lazy(1..=6, |x| x % 2 == 0);
+/// assert_eq!(odd.collect::<Vec<_>>(), vec![1, 3, 5]);
+/// assert_eq!(even.collect::<Vec<_>>(), vec![2, 4, 6]);
+/// ```
+pub fn partition_lazy<T, I, P>(iter: I, pred: P) -> (PartitionHandle<T>, PartitionHandle<T>)
+where
+    I: Iterator<Item = T> + 'static,
+    P: Fn(&T) -> bool + 'static,
+{
+    let iter: Box<dyn Iterator<Item = T>> = Box::new(iter);
+    let shared = Rc::new(RefCell::new(PartitionShared {
+        source: iter.fuse(),
+        pred: Box::new(pred),
+        buffers: [VecDeque::new(), VecDeque::new()],
+        alive: [true, true],
+    }));
+    (PartitionHandle { side: 0, shared: shared.clone() }, PartitionHandle { side: 1, shared })
+}
+
+impl<T> Iterator for PartitionHandle<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let mut shared = self.shared.borrow_mut();
+        if let Some(next) = shared.buffers[self.side].pop_front() {
+            return Some(next);
+        }
+        loop {
+            let next = shared.source.next()?;
+            let side = if (shared.pred)(&next) { 0 } else { 1 };
+            if side == self.side {
+                return Some(next);
+            }
+            if shared.alive[side] {
+                shared.buffers[side].push_back(next);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let shared = self.shared.borrow();
+        let buffered = shared.buffers[self.side].len();
+        let (_, upper) = shared.source.size_hint();
+        (buffered, upper.and_then(|x| x.checked_add(buffered)))
+    }
+}
+
+/// The source is fused, so PartitionHandle keeps returning None once it is exhausted.
+impl<T> FusedIterator for PartitionHandle<T> {}
+
+impl<T> Drop for PartitionHandle<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.alive[self.side] = false;
+        shared.buffers[self.side].clear();
+    }
+}
+
+/// Methods to combine any iterator with another one using the iterators of this crate.
+///
+/// Example
+///
+/// ```
+/// use iterutils::IterUtilsExt;
+/// let merged = vec![1, 4, 5].into_iter().merge_sorted_with(vec![2, 3].into_iter());
+/// assert_eq!(merged.collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+/// let chained = vec![1, 2].into_iter().seq_chain(vec![3].into_iter());
+/// assert_eq!(chained.collect::<Vec<_>>(), vec![1, 2, 3]);
+/// let alternated = vec![1, 2].into_iter().round_robin_with(vec![10, 20].into_iter());
+/// assert_eq!(alternated.collect::<Vec<_>>(), vec![1, 10, 2, 20]);
+/// ```
+pub trait IterUtilsExt: Iterator + Sized {
+    /// Merge with `other` in ascending order. Both iterators must be sorted ascending.
+    fn merge_sorted_with<'a>(self, other: impl Iterator<Item=Self::Item> + 'a) -> OrderedIterator<'a, Self::Item>
+        where Self: 'a, Self::Item: Ord + 'a
+    {
+        let mut o_iter = OrderedIterator::new_min();
+        o_iter.add(Box::new(self));
+        o_iter.add(Box::new(other));
+        o_iter
+    }
+
+    /// Yield all items of this iterator, then all items of `other`.
+    fn seq_chain<'a>(self, other: impl Iterator<Item=Self::Item> + 'a) -> SeqIter<'a, Self::Item>
+        where Self: 'a
+    {
+        let mut s_iter = SeqIter::new();
+        s_iter.add(Box::new(self));
+        s_iter.add(Box::new(other));
+        s_iter
+    }
+
+    /// Alternate between the items of this iterator and `other`.
+    fn round_robin_with<'a>(self, other: impl Iterator<Item=Self::Item> + 'a) -> RoundRobinIter<'a, Self::Item>
+        where Self: 'a
+    {
+        let mut rr_iter = RoundRobinIter::new();
+        rr_iter.add(Box::new(self));
+        rr_iter.add(Box::new(other));
+        rr_iter
+    }
+}
+
+impl<I: Iterator> IterUtilsExt for I {}
+
+/// Create a SeqIter from iterator expressions. Every expression is boxed and added in order.
+///
+/// Example
+///
+/// ```
+/// use iterutils::seq_iter;
+/// let s_iter = seq_iter![vec![1, 2].into_iter(), 3..5];
+/// assert_eq!(s_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+/// ```
+#[macro_export]
+macro_rules! seq_iter {
+    ($($iter:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut s_iter = $crate::SeqIter::new();
+        $( s_iter.add(Box::new($iter)); )*
+        s_iter
+    }};
+}
+
+/// Create an ascending OrderedIterator from sorted iterator expressions.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ordered_iter;
+/// let o_iter = ordered_iter![vec![1, 4].into_iter(), vec![2, 3].into_iter()];
+/// assert_eq!(o_iter.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+/// ```
+#[macro_export]
+macro_rules! ordered_iter {
+    ($($iter:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut o_iter = $crate::OrderedIterator::new_min();
+        $( o_iter.add(Box::new($iter)); )*
+        o_iter
+    }};
+}
+
+/// Create a descending OrderedIterator from iterator expressions sorted descending.
+///
+/// Example
+///
+/// ```
+/// use iterutils::ordered_iter_desc;
+/// let o_iter = ordered_iter_desc![vec![4, 1].into_iter(), vec![3, 2].into_iter()];
+/// assert_eq!(o_iter.collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+/// ```
+#[macro_export]
+macro_rules! ordered_iter_desc {
+    ($($iter:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut o_iter = $crate::OrderedIterator::new_max();
+        $( o_iter.add(Box::new($iter)); )*
+        o_iter
+    }};
+}
+
+/// Create a MultiIterator from a choose function and iterator expressions.
+///
+/// Example
+///
+/// ```
+/// use iterutils::multi_iter;
+/// // Always emit from the last source
+/// let m_iter = multi_iter!(|heads: &[i32]| Some(heads.len() - 1); vec![1, 2].into_iter(), vec![3].into_iter());
+/// assert_eq!(m_iter.collect::<Vec<_>>(), vec![3, 1, 2]);
+/// ```
+#[macro_export]
+macro_rules! multi_iter {
+    ($choose:expr $(; $($iter:expr),* $(,)?)?) => {{
+        #[allow(unused_mut)]
+        let mut m_iter = $crate::MultiIterator::new($choose);
+        $($( m_iter.add(Box::new($iter)); )*)?
+        m_iter
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seq_test() {
+        let v1 = vec![1,2,3,4,5];
+        let v2 = vec![6,7,8];
+        let v3 = vec![9,10];
+        let v4 = [1,2,4,5,6,7,8];
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(v1.into_iter()));
+        seq_iter.add(Box::new(v2.into_iter()));
+        seq_iter.add(Box::new(v3.into_iter()));
+        seq_iter.add(Box::new(v4.into_iter()));
+
+        for i in seq_iter {
+            println!("{i}");
+        }
+
+    }
+
+    #[test]
+    fn seq_with_capacity() {
+        let mut seq_iter = SeqIter::with_capacity(1);
+        seq_iter.add(Box::new(vec![1,2].into_iter()));
+        seq_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+    #[test]
+    fn seq_current_index() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        seq_iter.add(Box::new(vec![2].into_iter()));
+        assert_eq!(seq_iter.source_count(), 2);
+        assert_eq!(seq_iter.current_index(), 0);
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.current_index(), 0);
+        assert_eq!(seq_iter.next(), Some(2));
+        assert_eq!(seq_iter.current_index(), 1);
+        assert_eq!(seq_iter.next(), None);
+        assert_eq!(seq_iter.current_index(), 2);
+    }
+
+    #[test]
+    fn seq_add_front() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        seq_iter.add(Box::new(vec![2,3].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.next(), Some(2));
+        seq_iter.add_front(Box::new(vec![9].into_iter()));
+        assert_eq!(seq_iter.current_index(), 1);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![9,3]);
+    }
+
+    #[test]
+    fn seq_insert() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        seq_iter.add(Box::new(vec![2,3].into_iter()));
+        seq_iter.add(Box::new(vec![4].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.next(), Some(2));
+        // before the cursor: never consumed
+        seq_iter.insert(0, Box::new(vec![7].into_iter()));
+        assert_eq!(seq_iter.current_index(), 2);
+        // after the cursor: consumed in order
+        seq_iter.insert(3, Box::new(vec![8].into_iter()));
+        assert_eq!(seq_iter.source_count(), 5);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![3,8,4]);
+    }
+
+    #[test]
+    fn seq_insert_at_current() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,2,3].into_iter()));
+        seq_iter.add(Box::new(vec![4].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        let current = seq_iter.current_index();
+        seq_iter.insert(current, Box::new(vec![9].into_iter()));
+        assert_eq!(seq_iter.current_index(), current + 1);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![2,3,4]);
+    }
+
+    #[test]
+    fn seq_skip_current() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,-1,2].into_iter()));
+        seq_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.next(), Some(-1));
+        assert_eq!(seq_iter.skip_current(), 1);
+        assert_eq!(seq_iter.next(), Some(3));
+        assert_eq!(seq_iter.skip_current(), 0);
+        assert_eq!(seq_iter.skip_current(), 0);
+        assert_eq!(seq_iter.next(), None);
+    }
+
+    #[test]
+    fn seq_size_hint() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,2].into_iter()));
+        seq_iter.add(Box::new(vec![3,4,5].into_iter()));
+        assert_eq!(seq_iter.size_hint(), (5, Some(5)));
+        seq_iter.next();
+        seq_iter.next();
+        seq_iter.next();
+        assert_eq!(seq_iter.size_hint(), (2, Some(2)));
+        seq_iter.add(Box::new((0..).filter(|x| x % 2 == 0)));
+        assert_eq!(seq_iter.size_hint(), (2, None));
+    }
+
+    #[test]
+    fn seq_remaining_source_count() {
+        let mut seq_iter = SeqIter::new();
+        assert!(seq_iter.is_exhausted());
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        seq_iter.add(Box::new(vec![2].into_iter()));
+        assert_eq!(seq_iter.remaining_source_count(), 2);
+        assert!(!seq_iter.is_exhausted());
+        seq_iter.next();
+        seq_iter.next();
+        assert_eq!(seq_iter.remaining_source_count(), 1);
+        seq_iter.next();
+        assert_eq!(seq_iter.remaining_source_count(), 0);
+        assert!(seq_iter.is_exhausted());
+    }
+
+    #[test]
+    fn seq_default() {
+        let mut seq_iter: SeqIter<i32> = Default::default();
+        assert_eq!(seq_iter.source_count(), 0);
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn seq_from_vec() {
+        let iters: Vec<Box<dyn Iterator<Item = i32>>> = vec![
+            Box::new(vec![1,2].into_iter()),
+            Box::new(vec![3].into_iter()),
+        ];
+        let seq_iter = SeqIter::from_vec(iters);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1,2,3]);
+
+        let iters: Vec<Box<dyn Iterator<Item = i32>>> = vec![Box::new(0..2)];
+        let seq_iter: SeqIter<_> = iters.into();
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![0,1]);
+    }
+
+    #[test]
+    fn seq_exact_len() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,2].into_iter()));
+        seq_iter.add(Box::new([3,4,5].into_iter()));
+        assert_eq!(seq_iter.exact_len(), Some(5));
+        seq_iter.next();
+        seq_iter.next();
+        assert_eq!(seq_iter.exact_len(), Some(3));
+        seq_iter.add(Box::new((0..10).filter(|x| x % 2 == 0)));
+        assert_eq!(seq_iter.exact_len(), None);
+    }
+
+    #[test]
+    fn seq_add_many() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        let more: Vec<Box<dyn Iterator<Item = i32>>> = vec![
+            Box::new(vec![2,3].into_iter()),
+            Box::new(vec![4].into_iter()),
+        ];
+        seq_iter.add_many(more);
+        assert_eq!(seq_iter.source_count(), 3);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1,2,3,4]);
+    }
+
+    #[test]
+    fn seq_enumerate_global() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![10,11].into_iter()));
+        seq_iter.add(Box::new(std::iter::empty()));
+        seq_iter.add(Box::new(vec![12].into_iter()));
+        let tagged: Vec<_> = seq_iter.enumerate_global().collect();
+        assert_eq!(tagged, vec![(0,0,10), (0,1,11), (2,0,12)]);
+    }
+
+    #[test]
+    fn seq_map_sources() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,2].into_iter()));
+        seq_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        let mapped = seq_iter.map_sources(|iter| Box::new(iter.map(|x| x * 10)));
+        assert_eq!(mapped.collect::<Vec<_>>(), vec![20,30]);
+    }
+
+    #[test]
+    fn seq_on_source_exhausted() {
+        use std::{cell::RefCell, rc::Rc};
+        let exhausted = Rc::new(RefCell::new(vec![]));
+        let log = exhausted.clone();
+        let mut seq_iter = SeqIter::new().on_source_exhausted(move |i| log.borrow_mut().push(i));
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        seq_iter.add(Box::new(std::iter::empty()));
+        seq_iter.add(Box::new(vec![2].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert!(exhausted.borrow().is_empty());
+        assert_eq!(seq_iter.next(), Some(2));
+        assert_eq!(*exhausted.borrow(), vec![0,1]);
+        assert_eq!(seq_iter.next(), None);
+        assert_eq!(*exhausted.borrow(), vec![0,1,2]);
+    }
+
+    #[test]
+    fn seq_fused() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        for _ in 0..10 {
+            assert_eq!(seq_iter.next(), None);
+        }
+        // Adding after the first None does not revive it
+        seq_iter.add(Box::new(vec![2].into_iter()));
+        seq_iter.add_front(Box::new(vec![3].into_iter()));
+        assert_eq!(seq_iter.size_hint(), (0, Some(0)));
+        assert!(seq_iter.is_exhausted());
+        assert_eq!(seq_iter.current_index(), seq_iter.source_count());
+        assert_eq!(seq_iter.remaining_source_count(), 0);
+        assert_eq!(seq_iter.skip_current(), 0);
+        assert_eq!(seq_iter.current_index(), 3);
+        assert_eq!(seq_iter.next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn seq_insert_out_of_bounds() {
+        let mut seq_iter: SeqIter<i32> = SeqIter::new();
+        seq_iter.insert(1, Box::new(std::iter::empty()));
+    }
+
+
+    #[test]
+    fn test_multi_1() {
+        let v1: Vec<i32> = vec![1,2,3,4,5,11,19];
+        let v2: Vec<i32> = vec![1,7,8,12,44,231];
+        let v3: Vec<i32> = vec![3,5,7,9,10,1000];
+        let v4: [i32; 7] = [1,2,4,5,6,7,8];
+
+
+        let choose_fn = |x:&[i32]| -> Option<usize> {
+            let result = x.iter().enumerate().min_by(|x, y| {
+                x.1.cmp(y.1)
+            }).map(|x| x.0);
+            println!("Choosing from {:?} -> {:?}", x, result);
+            result
         };
 
-        let mut min_iter = MultiIterator::new(choose_fn);
-        min_iter.add(Box::new(v1.into_iter()));
-        min_iter.add(Box::new(v2.into_iter()));
-        min_iter.add(Box::new(v3.into_iter()));
-        min_iter.add(Box::new(v4.into_iter()));
+        let mut min_iter = MultiIterator::new(choose_fn);
+        min_iter.add(Box::new(v1.into_iter()));
+        min_iter.add(Box::new(v2.into_iter()));
+        min_iter.add(Box::new(v3.into_iter()));
+        min_iter.add(Box::new(v4.into_iter()));
+
+        for i in min_iter{
+            println!("{i}");
+        }
+
+
+    }
+
+
+    #[test]
+    fn test_multi_stateful_choose() {
+        // round robin over the sources
+        let mut turn = 0;
+        let mut rr_iter = MultiIterator::new(move |heads: &[i32]| {
+            turn += 1;
+            Some(turn % heads.len())
+        });
+        rr_iter.add(Box::new(vec![1,2,3].into_iter()));
+        rr_iter.add(Box::new(vec![10,20,30].into_iter()));
+        assert_eq!(rr_iter.collect::<Vec<_>>(), vec![10,1,20,2,30,3]);
+
+        fn first(_: &[i32]) -> Option<usize> {
+            Some(0)
+        }
+        let mut first_iter = MultiIterator::new_fn(first);
+        first_iter.add(Box::new(vec![1,2].into_iter()));
+        first_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(first_iter.collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+
+    #[test]
+    fn test_multi_len() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        assert!(m_iter.is_empty());
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(vec![2,3].into_iter()));
+        m_iter.add(Box::new(std::iter::empty()));
+        assert_eq!(m_iter.len(), 2);
+        assert_eq!(m_iter.next(), Some(1));
+        assert_eq!(m_iter.len(), 1);
+        m_iter.next();
+        m_iter.next();
+        assert!(m_iter.is_empty());
+    }
+
+
+    #[test]
+    fn test_multi_head_snapshot() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(vec![5].into_iter()));
+        assert_eq!(m_iter.head_snapshot(), &[1,5]);
+        m_iter.next();
+        assert_eq!(m_iter.head_snapshot(), &[2,5]);
+        m_iter.next();
+        m_iter.next();
+        assert!(m_iter.head_snapshot().is_empty());
+    }
+
+
+    #[test]
+    fn test_multi_remove_source() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(vec![5,6].into_iter()));
+        m_iter.add(Box::new(vec![9].into_iter()));
+        assert_eq!(m_iter.remove_source(3), Err(IndexError { index: 3, len: 3 }));
+        assert_eq!(m_iter.remove_source(0), Ok(()));
+        assert_eq!(m_iter.head_snapshot(), &[9,5]);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![9,5,6]);
+    }
+
+
+    #[test]
+    fn test_multi_add_many() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        let sources: Vec<Box<dyn Iterator<Item = i32>>> = vec![
+            Box::new(vec![1].into_iter()),
+            Box::new(std::iter::empty()),
+            Box::new(vec![2,3].into_iter()),
+        ];
+        m_iter.add_many(sources);
+        assert_eq!(m_iter.head_snapshot(), &[1,2]);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+
+    #[test]
+    fn test_multi_by_key() {
+        let mut min_iter = MultiIterator::min_by_key(|x: &(i32, char)| x.0);
+        min_iter.add(Box::new(vec![(1,'a'), (3,'a')].into_iter()));
+        min_iter.add(Box::new(vec![(1,'b'), (2,'b')].into_iter()));
+        assert_eq!(min_iter.collect::<Vec<_>>(), vec![(1,'a'), (1,'b'), (2,'b'), (3,'a')]);
+
+        let mut max_iter = MultiIterator::max_by_key(|x: &(i32, char)| x.0);
+        max_iter.add(Box::new(vec![(3,'a'), (1,'a')].into_iter()));
+        max_iter.add(Box::new(vec![(3,'b'), (2,'b')].into_iter()));
+        assert_eq!(max_iter.collect::<Vec<_>>(), vec![(3,'a'), (3,'b'), (2,'b'), (1,'a')]);
+    }
+
+
+    #[test]
+    fn test_multi_exhausted_slot_reused() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(vec![2].into_iter()));
+        m_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(m_iter.next(), Some(1));
+        // the last source moved into the first slot
+        assert_eq!(m_iter.head_snapshot(), &[3,2]);
+    }
+
+
+    #[test]
+    fn test_multi_fused() {
+        // gives up once, then would pick again
+        let mut calls = 0;
+        let mut m_iter = MultiIterator::new(move |_: &[i32]| {
+            calls += 1;
+            if calls == 2 { None } else { Some(0) }
+        });
+        m_iter.add(Box::new(vec![1,2,3].into_iter()));
+        assert_eq!(m_iter.next(), Some(1));
+        assert_eq!(m_iter.next(), None);
+        for _ in 0..10 {
+            assert_eq!(m_iter.next(), None);
+        }
+        // Neither new sources nor a new choose function revive it
+        let pulled = Rc::new(std::cell::Cell::new(0));
+        let counter = pulled.clone();
+        m_iter.add(Box::new(vec![4].into_iter().inspect(move |_| counter.set(counter.get() + 1))));
+        assert_eq!(pulled.get(), 0);
+        assert_eq!(m_iter.len(), 1);
+        m_iter.replace_choose_fn(Box::new(|_: &[i32]| Some(0)));
+        assert_eq!(m_iter.size_hint(), (0, Some(0)));
+        assert_eq!(m_iter.next(), None);
+    }
+
+
+    #[test]
+    fn test_multi_size_hint() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2,3].into_iter()));
+        m_iter.add(Box::new(vec![4].into_iter()));
+        assert_eq!(m_iter.size_hint(), (0, Some(4)));
+        m_iter.next();
+        assert_eq!(m_iter.size_hint(), (0, Some(3)));
+        m_iter.add(Box::new(0..));
+        assert_eq!(m_iter.size_hint(), (0, None));
+    }
+
+
+    #[test]
+    fn test_multi_debug() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(vec![5].into_iter()));
+        assert_eq!(format!("{:?}", m_iter), "MultiIterator { sources: 2, head: [1, 5], source_ids: [0, 1] }");
+        m_iter.add(Box::new(vec![7].into_iter()));
+        m_iter.next();
+        m_iter.next();
+        assert_eq!(format!("{:?}", m_iter), "MultiIterator { sources: 2, head: [7, 5], source_ids: [2, 1] }");
+    }
+
+
+    #[test]
+    fn test_multi_on_exhausted() {
+        use std::{cell::RefCell, rc::Rc};
+        let exhausted = Rc::new(RefCell::new(vec![]));
+        let log = exhausted.clone();
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0))
+            .with_on_exhausted(move |i| log.borrow_mut().push(i));
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(std::iter::empty()));
+        m_iter.add(Box::new(vec![2].into_iter()));
+        m_iter.add(Box::new(vec![3,4].into_iter()));
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![1,3,4,2]);
+        assert_eq!(*exhausted.borrow(), vec![0,3,2]);
+    }
+
+
+    #[test]
+    fn test_multi_replace_choose_fn() {
+        let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+        m_iter.add(Box::new(vec![1,3,5].into_iter()));
+        m_iter.add(Box::new(vec![6,4,2].into_iter()));
+        assert_eq!(m_iter.next(), Some(1));
+        m_iter.replace_choose_fn(Box::new(|heads: &[i32]| {
+            heads.iter().enumerate().max_by_key(|x| *x.1).map(|x| x.0)
+        }));
+        assert_eq!(m_iter.head_snapshot(), &[3,6]);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![6,4,3,5,2]);
+    }
+
+
+    #[test]
+    fn test_multi_enumerate_sources() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(std::iter::empty()));
+        m_iter.add(Box::new(vec![2].into_iter()));
+        m_iter.add(Box::new(vec![3,4].into_iter()));
+        // source 3 moves into slot 0 once source 0 is exhausted
+        let tagged: Vec<_> = m_iter.enumerate_sources().collect();
+        assert_eq!(tagged, vec![(0,1), (3,3), (3,4), (2,2)]);
+    }
+
+    #[test]
+    fn test_multi_enumerate_sources_remove() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(vec![10,20].into_iter()));
+        m_iter.add(Box::new(vec![100].into_iter()));
+        let mut tagged = m_iter.enumerate_sources();
+        assert_eq!(tagged.next(), Some((0,1)));
+        tagged.remove_source(0).unwrap();
+        tagged.add(Box::new(vec![1000].into_iter()));
+        assert!(tagged.remove_source(3).is_err());
+        // source 3 moves into slot 0 once source 2 is exhausted
+        assert_eq!(tagged.collect::<Vec<_>>(), vec![(2,100), (3,1000), (1,10), (1,20)]);
+    }
+
+
+    #[test]
+    fn test_ordered() {
+        let v1 = vec![1,2,3,4,5,11,19];
+        let v2 = vec![1,7,8,12,44,231];
+        let v3 = vec![3,5,7,9,10,1000];
+        let v4 = [1,2,4,5,6,7,8];
+
+        let mut o_iter: OrderedIterator<_> = OrderedIterator::new_min();
+        o_iter.add(Box::new(v1.into_iter()));
+        o_iter.add(Box::new(v2.into_iter()));
+        o_iter.add(Box::new(v3.into_iter()));
+        o_iter.add(Box::new(v4.into_iter()));
+
+        for i in o_iter{
+            println!("{i}");
+        }
+
+        let v1 = vec!(9,4,2);
+        let v2:Vec<i32> = vec!();
+        let v3 = vec!(999,22,3,1);
+        let v4 = [992,222,211,20,19,2,1];
+
+        let mut o_iter = OrderedIterator::new_max();
+        o_iter.add(Box::new(v1.into_iter()));
+        o_iter.add(Box::new(v2.into_iter()));
+        o_iter.add(Box::new(v3.into_iter()));
+        o_iter.add(Box::new(v4.into_iter()));
+        for i in o_iter {
+            println!("{i}");
+        }
+    }
+
+    #[test]
+    fn test_ordered_stable() {
+        // Ordered by the number only, the char tells the source
+        #[derive(Debug, PartialEq, Eq)]
+        struct Keyed(i32, char);
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Keyed {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+        let tags = |items: Vec<Keyed>| items.iter().map(|x| (x.0, x.1)).collect::<Vec<_>>();
+
+        let mut o_iter = OrderedIterator::new_min_stable();
+        o_iter.add(Box::new(vec![Keyed(1,'a'), Keyed(2,'a'), Keyed(2,'a')].into_iter()));
+        o_iter.add(Box::new(vec![Keyed(1,'b'), Keyed(2,'b')].into_iter()));
+        o_iter.add(Box::new(vec![Keyed(0,'c'), Keyed(1,'c'), Keyed(2,'c')].into_iter()));
+        assert_eq!(tags(o_iter.collect()), vec![
+            (0,'c'), (1,'a'), (1,'b'), (1,'c'), (2,'a'), (2,'a'), (2,'b'), (2,'c')
+        ]);
+
+        let mut o_iter = OrderedIterator::new_max_stable();
+        o_iter.add(Box::new(vec![Keyed(3,'a'), Keyed(1,'a')].into_iter()));
+        o_iter.add(Box::new(vec![Keyed(3,'b'), Keyed(2,'b')].into_iter()));
+        assert_eq!(tags(o_iter.collect()), vec![(3,'a'), (3,'b'), (2,'b'), (1,'a')]);
+
+        // A source added later reuses the slot of an exhausted one, but still
+        // comes after the sources added before it
+        let mut o_iter = OrderedIterator::new_min_stable();
+        o_iter.add(Box::new(vec![Keyed(0,'a')].into_iter()));
+        o_iter.add(Box::new(vec![Keyed(1,'b')].into_iter()));
+        assert_eq!(o_iter.next(), Some(Keyed(0,'a')));
+        o_iter.add(Box::new(vec![Keyed(1,'c')].into_iter()));
+        assert_eq!(tags(o_iter.collect()), vec![(1,'b'), (1,'c')]);
+    }
+
+    #[test]
+    fn test_ordered_by_key() {
+        // floats have no Ord, but their integer part does
+        let mut o_iter = OrderedIterator::new_by_key(|x: &f64| *x as i64);
+        o_iter.add(Box::new(vec![1.5, 3.5].into_iter()));
+        o_iter.add(Box::new(vec![2.5].into_iter()));
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1.5, 2.5, 3.5]);
+    }
+
+    #[test]
+    fn test_ordered_peek() {
+        let mut o_iter = OrderedIterator::new_min();
+        assert_eq!(o_iter.peek(), None);
+        o_iter.add(Box::new(vec![2,4].into_iter()));
+        o_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(o_iter.peek(), Some(&2));
+        assert_eq!(o_iter.peek(), Some(&2));
+        assert_eq!(o_iter.next(), Some(2));
+        assert_eq!(o_iter.peek(), Some(&3));
+        // a new source can change what comes next
+        o_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(o_iter.peek(), Some(&1));
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1,3,4]);
+    }
+
+    #[test]
+    fn test_ordered_add_many() {
+        let mut o_iter = OrderedIterator::new_min();
+        let sources: Vec<Box<dyn Iterator<Item = i32>>> = vec![
+            Box::new(vec![1,4].into_iter()),
+            Box::new(std::iter::empty()),
+            Box::new(vec![2,3].into_iter()),
+        ];
+        o_iter.add_many(sources);
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1,2,3,4]);
+    }
+
+    #[test]
+    fn test_ordered_fused() {
+        let mut o_iter = OrderedIterator::new_max();
+        o_iter.add(Box::new(vec![2,1].into_iter()));
+        assert_eq!(o_iter.next(), Some(2));
+        assert_eq!(o_iter.next(), Some(1));
+        for _ in 0..10 {
+            assert_eq!(o_iter.next(), None);
+        }
+        // Adding after the first None does not revive it
+        let pulled = Rc::new(std::cell::Cell::new(0));
+        let counter = pulled.clone();
+        o_iter.add(Box::new(vec![3].into_iter().inspect(move |_| counter.set(counter.get() + 1))));
+        assert_eq!(pulled.get(), 0);
+        assert_eq!(o_iter.peek(), None);
+        assert_eq!(o_iter.size_hint(), (0, Some(0)));
+        assert_eq!(o_iter.next(), None);
+    }
+
+    #[test]
+    fn test_ordered_size_hint() {
+        let mut o_iter = OrderedIterator::new_min();
+        o_iter.add(Box::new(vec![1,3,5].into_iter()));
+        o_iter.add(Box::new(vec![2,4].into_iter()));
+        assert_eq!(o_iter.size_hint(), (5, Some(5)));
+        o_iter.next();
+        o_iter.next();
+        assert_eq!(o_iter.size_hint(), (3, Some(3)));
+        o_iter.add(Box::new((10..).filter(|x| x % 2 == 0)));
+        assert_eq!(o_iter.size_hint(), (4, None));
+    }
+
+    #[test]
+    fn test_ordered_from_sorted_vecs() {
+        let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1,5], vec![], vec![2,3,4]]);
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1,2,3,4,5]);
+        let o_iter = OrderedIterator::from_sorted_vecs_desc(vec![vec![5,1], vec![4,3,2]]);
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![5,4,3,2,1]);
+    }
+
+    #[test]
+    fn test_ordered_debug() {
+        let mut o_iter = OrderedIterator::new_min();
+        o_iter.add(Box::new(vec![1,2].into_iter()));
+        o_iter.add(Box::new(std::iter::empty()));
+        assert_eq!(format!("{:?}", o_iter),
+            "OrderedIterator { sources: 1, next: Some(1), heap: [HeapItem { what: 1, iter_index: 0 }] }");
+        o_iter.next();
+        o_iter.next();
+        assert_eq!(format!("{:?}", o_iter), "OrderedIterator { sources: 0, next: None, heap: [] }");
+    }
+
+    #[test]
+    fn test_ordered_dedup() {
+        let mut o_iter = OrderedIterator::new_max();
+        o_iter.add(Box::new(vec![5,5,3,1].into_iter()));
+        o_iter.add(Box::new(vec![5,4,3].into_iter()));
+        o_iter.add(Box::new(vec![1,1].into_iter()));
+        assert_eq!(o_iter.dedup().collect::<Vec<_>>(), vec![5,4,3,1]);
+    }
 
-        for i in min_iter{
-            println!("{i}");
-        }
+    #[test]
+    fn test_ordered_dedup_by() {
+        let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![(1,'x'), (2,'x')], vec![(1,'y'), (2,'y'), (3,'y')]]);
+        let firsts: Vec<_> = o_iter.dedup_by_key(|x| x.0).collect();
+        assert_eq!(firsts, vec![(1,'x'), (2,'x'), (3,'y')]);
 
+        // collapse runs of numbers less than 3 apart
+        let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1,2,10], vec![3,11,20]]);
+        let runs: Vec<_> = o_iter.dedup_by(|first, x| x - first < 3).collect();
+        assert_eq!(runs, vec![1,10,20]);
+    }
+
+    #[test]
+    fn test_ordered_checked() {
+        let mut o_iter = OrderedIterator::new_min_checked();
+        o_iter.add(Box::new(vec![1,4,6].into_iter()));
+        o_iter.add(Box::new(vec![2,3,1].into_iter()));
+        o_iter.add(Box::new(vec![5].into_iter()));
+        assert_eq!(o_iter.next(), Some(1));
+        assert_eq!(o_iter.next(), Some(2));
+        assert_eq!(o_iter.next(), Some(3));
+        // The error ends the iteration, although other heads are left
+        assert_eq!(o_iter.peek(), None);
+        assert_eq!(o_iter.source_count(), 0);
+        assert!(format!("{:?}", o_iter).starts_with("OrderedIterator { sources: 0, next: None,"));
+        assert_eq!(o_iter.next(), None);
+        assert_eq!(o_iter.take_error(), Some(UnsortedInputError { source_index: 1 }));
+        assert_eq!(o_iter.take_error(), None);
+        assert_eq!(o_iter.next(), None);
 
+        let mut o_iter = OrderedIterator::new_min_checked();
+        o_iter.add(Box::new(vec![1,1,2].into_iter()));
+        assert_eq!(o_iter.by_ref().collect::<Vec<_>>(), vec![1,1,2]);
+        assert_eq!(o_iter.take_error(), None);
     }
 
+    #[test]
+    fn test_ordered_source_count() {
+        let mut o_iter = OrderedIterator::new_min();
+        o_iter.add(Box::new(vec![1,3].into_iter()));
+        o_iter.add(Box::new(vec![2].into_iter()));
+        o_iter.add(Box::new(std::iter::empty()));
+        assert_eq!(o_iter.source_count(), 2);
+        o_iter.next();
+        o_iter.next();
+        assert_eq!(o_iter.source_count(), 1);
+        o_iter.next();
+        assert_eq!(o_iter.source_count(), 0);
+    }
 
     #[test]
-    fn test_ordered() {
-        let v1 = vec![1,2,3,4,5,11,19];
-        let v2 = vec![1,7,8,12,44,231];
-        let v3 = vec![3,5,7,9,10,1000];
-        let v4 = [1,2,4,5,6,7,8];
+    fn test_ordered_slot_reuse() {
+        let mut o_iter = OrderedIterator::new_min();
+        o_iter.add(Box::new(vec![1].into_iter()));
+        o_iter.add(Box::new(vec![2,5].into_iter()));
+        assert_eq!(o_iter.next(), Some(1));
+        assert_eq!(o_iter.free_slots, vec![0]);
+        o_iter.add(Box::new(vec![3,4].into_iter()));
+        assert!(o_iter.free_slots.is_empty());
+        assert_eq!(o_iter.iters.len(), 2);
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![2,3,4,5]);
+    }
 
-        let mut o_iter: OrderedIterator<_> = OrderedIterator::new_min();
-        o_iter.add(Box::new(v1.into_iter()));
-        o_iter.add(Box::new(v2.into_iter()));
-        o_iter.add(Box::new(v3.into_iter()));
-        o_iter.add(Box::new(v4.into_iter()));
+    #[test]
+    fn test_ordered_with_comparator() {
+        // by length, then alphabetically
+        let mut o_iter = OrderedIterator::new_with_comparator(|x: &&str, y: &&str| {
+            x.len().cmp(&y.len()).then(x.cmp(y))
+        });
+        o_iter.add(Box::new(vec!["b", "aa", "ccc"].into_iter()));
+        o_iter.add(Box::new(vec!["a", "bb"].into_iter()));
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec!["a", "b", "aa", "bb", "ccc"]);
+    }
 
-        for i in o_iter{
-            println!("{i}");
+    #[test]
+    fn test_round_robin() {
+        let mut rr_iter: RoundRobinIter<i32> = RoundRobinIter::new();
+        rr_iter.add(Box::new(vec![1,2].into_iter()));
+        rr_iter.add(Box::new(std::iter::empty()));
+        rr_iter.add(Box::new(vec![10,20,30].into_iter()));
+        rr_iter.add(Box::new(vec![100].into_iter()));
+        assert_eq!(rr_iter.size_hint(), (6, Some(6)));
+        assert_eq!(rr_iter.next(), Some(1));
+        assert_eq!(rr_iter.next(), Some(10));
+        assert_eq!(rr_iter.source_count(), 3);
+        assert_eq!(rr_iter.collect::<Vec<_>>(), vec![100,2,20,30]);
+
+        // Fused: iterators added after the end are never consumed
+        let mut rr_iter: RoundRobinIter<i32> = RoundRobinIter::new();
+        assert_eq!(rr_iter.next(), None);
+        rr_iter.add(Box::new(vec![0].into_iter()));
+        assert_eq!(rr_iter.source_count(), 0);
+        assert_eq!(rr_iter.next(), None);
+    }
+
+    #[test]
+    fn test_weighted_round_robin() {
+        let mut wrr_iter = WeightedRoundRobinIter::new();
+        wrr_iter.add(Box::new(vec![1,2,3].into_iter()), 3);
+        wrr_iter.add(Box::new(vec![7,7].into_iter()), 0);
+        wrr_iter.add(Box::new(vec![10,20,30].into_iter()), 1);
+        wrr_iter.add(Box::new(std::iter::empty()), 2);
+        wrr_iter.add(Box::new(vec![100,200,300].into_iter()), 2);
+        assert_eq!(wrr_iter.source_count(), 4);
+        assert_eq!(wrr_iter.collect::<Vec<_>>(), vec![1,2,3,10,100,200,20,300,30]);
+
+        // Fused: iterators added after the end are never consumed
+        let mut wrr_iter: WeightedRoundRobinIter<i32> = WeightedRoundRobinIter::new();
+        assert_eq!(wrr_iter.next(), None);
+        wrr_iter.add(Box::new(vec![0].into_iter()), 1);
+        assert_eq!(wrr_iter.source_count(), 0);
+        assert_eq!(wrr_iter.next(), None);
+    }
+
+    #[test]
+    fn test_zip_multi() {
+        let mut zip_iter: ZipMultiIter<i32> = ZipMultiIter::new();
+        assert_eq!(zip_iter.next(), None);
+        zip_iter.add(Box::new(vec![0].into_iter()));
+        assert_eq!(zip_iter.size_hint(), (0, Some(0)));
+        assert_eq!(zip_iter.next(), None);
+
+        let mut zip_iter = ZipMultiIter::new();
+        zip_iter.add(Box::new(vec![1,2,3].into_iter()));
+        zip_iter.add(Box::new(vec![4,5].into_iter()));
+        assert_eq!(zip_iter.size_hint(), (2, Some(2)));
+        assert_eq!(zip_iter.next(), Some(vec![1,4]));
+        assert_eq!(zip_iter.next(), Some(vec![2,5]));
+        assert_eq!(zip_iter.next(), None);
+        assert_eq!(zip_iter.next(), None);
+        assert_eq!(zip_iter.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_zip_longest_multi() {
+        let mut zip_iter: ZipLongestMultiIter<i32> = ZipLongestMultiIter::new();
+        assert_eq!(zip_iter.next(), None);
+
+        let mut zip_iter = ZipLongestMultiIter::new();
+        zip_iter.add(Box::new(vec![1].into_iter()));
+        zip_iter.add(Box::new(Vec::new().into_iter()));
+        zip_iter.add(Box::new(vec![4,5,6].into_iter()));
+        assert_eq!(zip_iter.size_hint(), (3, Some(3)));
+        assert_eq!(zip_iter.next(), Some(vec![Some(1), None, Some(4)]));
+        assert_eq!(zip_iter.next(), Some(vec![None, None, Some(5)]));
+        assert_eq!(zip_iter.next(), Some(vec![None, None, Some(6)]));
+        assert_eq!(zip_iter.next(), None);
+        assert_eq!(zip_iter.next(), None);
+        // Fused: iterators added after the end are never consumed
+        zip_iter.add(Box::new(vec![7].into_iter()));
+        assert_eq!(zip_iter.size_hint(), (0, Some(0)));
+        assert_eq!(zip_iter.next(), None);
+    }
+
+    #[test]
+    fn test_tee() {
+        let mut handles = tee(vec![1,2,3].into_iter(), 3);
+        let mut third = handles.pop().unwrap();
+        let mut second = handles.pop().unwrap();
+        let mut first = handles.pop().unwrap();
+        assert_eq!(first.next(), Some(1));
+        assert_eq!(first.next(), Some(2));
+        assert_eq!(second.size_hint(), (3, Some(3)));
+        assert_eq!(second.next(), Some(1));
+        assert_eq!(third.next(), Some(1));
+        drop(third);
+        assert_eq!(first.next(), Some(3));
+        assert_eq!(first.next(), None);
+        let second = std::thread::spawn(move || second.collect::<Vec<_>>()).join().unwrap();
+        assert_eq!(second, vec![2,3]);
+
+        assert!(tee(vec![1].into_iter(), 0).is_empty());
+
+        // Borrowed and !Send sources can be teed too
+        let v = [1,2];
+        let handles = tee(v.iter().cloned(), 2);
+        assert_eq!(handles.into_iter().flatten().collect::<Vec<_>>(), vec![1,2,1,2]);
+        let rc = std::rc::Rc::new(5);
+        let handles = tee(std::iter::once(rc.clone()).map(|x| *x), 2);
+        assert_eq!(handles.into_iter().flatten().collect::<Vec<_>>(), vec![5,5]);
+    }
+
+    #[test]
+    fn test_interleaved() {
+        let interleaved = InterleavedIter::new(Box::new(vec![1].into_iter()), Box::new(vec![10,20,30].into_iter()));
+        assert_eq!(interleaved.size_hint(), (4, Some(4)));
+        assert_eq!(interleaved.collect::<Vec<_>>(), vec![1,10,20,30]);
+
+        let mut interleaved: InterleavedIter<i32> = InterleavedIter::new(Box::new(std::iter::empty()), Box::new(std::iter::empty()));
+        assert_eq!(interleaved.next(), None);
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let left = vec![1,1,2,4,5,5,7];
+        let right = vec![0,1,1,3,5,6,7,7];
+        let joined = MergeJoinIter::new(Box::new(left.into_iter()), Box::new(right.into_iter()), |x| *x, |x| *x);
+        assert_eq!(joined.collect::<Vec<_>>(), vec![(1,1),(1,1),(1,1),(1,1),(5,5),(5,5),(7,7),(7,7)]);
+
+        let mut joined = MergeJoinIter::new(Box::new(vec![1,2].into_iter()), Box::new(vec![3].into_iter()), |x| *x, |x: &i32| *x);
+        assert_eq!(joined.next(), None);
+        assert_eq!(joined.next(), None);
+
+        // Right source yields 1, None, 2, None, ... and must not be polled after its first None
+        let mut count = 0;
+        let right = std::iter::from_fn(move || {
+            count += 1;
+            if count % 2 == 1 { Some(count / 2 + 1) } else { None }
+        });
+        let joined = MergeJoinIter::new(Box::new(vec![1,2].into_iter()), Box::new(right), |x| *x, |x: &i32| *x);
+        assert_eq!(joined.collect::<Vec<_>>(), vec![(1,1)]);
+    }
+
+    #[test]
+    fn test_left_join() {
+        let left = vec![1,3,3,5,8];
+        let right = vec![0,3,4,5,5,6];
+        let joined = LeftJoinIter::new(Box::new(left.into_iter()), Box::new(right.into_iter()), |x| *x, |x| *x);
+        assert_eq!(joined.size_hint(), (5, Some(5)));
+        assert_eq!(joined.collect::<Vec<_>>(), vec![(1,None),(3,Some(3)),(3,Some(3)),(5,Some(5)),(8,None)]);
+    }
+
+    #[test]
+    fn test_union_sorted() {
+        let mut union = UnionSortedIter::new();
+        union.add(Box::new(vec![1,1,4].into_iter()));
+        union.add(Box::new(Vec::new().into_iter()));
+        union.add(Box::new(vec![0,1,4,9].into_iter()));
+        union.add(Box::new(vec![4].into_iter()));
+        assert_eq!(union.collect::<Vec<_>>(), vec![0,1,4,9]);
+
+        // Polling an empty union ends it
+        let mut union = UnionSortedIter::new();
+        assert_eq!(union.next(), None);
+        union.add(Box::new(vec![1].into_iter()));
+        assert_eq!(union.next(), None);
+    }
+
+    #[test]
+    fn test_intersection_sorted() {
+        let mut intersection: IntersectionSortedIter<i32> = IntersectionSortedIter::new();
+        assert_eq!(intersection.next(), None);
+        intersection.add(Box::new(vec![1].into_iter()));
+        assert_eq!(intersection.size_hint(), (0, Some(0)));
+        assert_eq!(intersection.next(), None);
+
+        let mut intersection = IntersectionSortedIter::new();
+        intersection.add(Box::new(vec![1,1,1,2,6,7,9].into_iter()));
+        intersection.add(Box::new(vec![1,1,3,6,8,9].into_iter()));
+        intersection.add(Box::new(vec![0,1,1,5,6,9,10].into_iter()));
+        intersection.add(Box::new(vec![1,1,4,6,9].into_iter()));
+        assert_eq!(intersection.size_hint(), (0, Some(5)));
+        assert_eq!(intersection.collect::<Vec<_>>(), vec![1,1,6,9]);
+
+        let mut intersection = IntersectionSortedIter::new();
+        intersection.add(Box::new(vec![1,2].into_iter()));
+        intersection.add(Box::new(Vec::new().into_iter()));
+        assert_eq!(intersection.next(), None);
+        assert_eq!(intersection.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_difference_sorted() {
+        let difference = DifferenceSortedIter::new(Box::new(vec![0,1,1,4,6,6,7,9].into_iter()), Box::new(vec![1,2,6,8,9,10].into_iter()));
+        assert_eq!(difference.size_hint(), (0, Some(8)));
+        assert_eq!(difference.collect::<Vec<_>>(), vec![0,4,7]);
+
+        let difference = DifferenceSortedIter::new(Box::new(vec![1,2].into_iter()), Box::new(Vec::new().into_iter()));
+        assert_eq!(difference.collect::<Vec<_>>(), vec![1,2]);
+    }
+
+    #[test]
+    fn test_symmetric_difference_sorted() {
+        let sym_diff = SymmetricDifferenceSortedIter::new(Box::new(vec![0,1,1,4,6,9].into_iter()), Box::new(vec![1,2,6,9,10].into_iter()));
+        assert_eq!(sym_diff.size_hint(), (0, Some(11)));
+        assert_eq!(sym_diff.collect::<Vec<_>>(), vec![0,1,2,4,10]);
+
+        let mut sym_diff = SymmetricDifferenceSortedIter::new(Box::new(vec![3].into_iter()), Box::new(vec![3].into_iter()));
+        assert_eq!(sym_diff.next(), None);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut groups = GroupByIter::new(Box::new(vec![1,3,2,4,4,5].into_iter()), |x| x % 2);
+        assert_eq!(groups.size_hint(), (1, Some(6)));
+        assert_eq!(groups.next(), Some((1, vec![1,3])));
+        assert_eq!(groups.next(), Some((0, vec![2,4,4])));
+        assert_eq!(groups.next(), Some((1, vec![5])));
+        assert_eq!(groups.next(), None);
+        assert_eq!(groups.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_chunked() {
+        let mut chunks = ChunkedIter::new(Box::new(1..=6), 3);
+        assert_eq!(chunks.size_hint(), (2, Some(2)));
+        assert_eq!(chunks.next(), Some(vec![1,2,3]));
+        assert_eq!(chunks.next(), Some(vec![4,5,6]));
+        assert_eq!(chunks.next(), None);
+
+        let chunks = ChunkedIter::new(Box::new(1..=3), 5);
+        assert_eq!(chunks.collect::<Vec<_>>(), vec![vec![1,2,3]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_chunked_zero_size() {
+        ChunkedIter::new(Box::new(1..=3), 0);
+    }
+
+    #[test]
+    fn test_window() {
+        let mut windows = WindowIter::new(Box::new(1..=4), 3);
+        assert_eq!(windows.size_hint(), (2, Some(2)));
+        assert_eq!(windows.next(), Some(vec![1,2,3]));
+        assert_eq!(windows.size_hint(), (1, Some(1)));
+        assert_eq!(windows.next(), Some(vec![2,3,4]));
+        assert_eq!(windows.next(), None);
+        assert_eq!(windows.size_hint(), (0, Some(0)));
+
+        let mut windows = WindowIter::new(Box::new(1..=2), 3);
+        assert_eq!(windows.size_hint(), (0, Some(0)));
+        assert_eq!(windows.next(), None);
+    }
+
+    #[test]
+    fn test_batch_by() {
+        let batches = BatchByIter::new(vec!["a1", "a2", "b1", "a3"].into_iter(), |x| x.as_bytes()[0]);
+        assert_eq!(batches.collect::<Vec<_>>(), vec![(b'a', vec!["a1", "a2"]), (b'b', vec!["b1"]), (b'a', vec!["a3"])]);
+
+        let batches = BatchByIter::new(1..=7, |x| *x < 6).max_batch_size(2);
+        assert_eq!(batches.collect::<Vec<_>>(), vec![(true, vec![1,2]), (true, vec![3,4]), (true, vec![5]), (false, vec![6,7])]);
+    }
+
+    #[test]
+    fn test_cached_seq() {
+        let mut cached = CachedSeqIter::new(SeqIter::from_vec(vec![Box::new(vec![1,2,3,4].into_iter())]));
+        assert_eq!(cached.next(), Some(1));
+        assert_eq!(cached.next(), Some(2));
+        assert_eq!(cached.next(), Some(3));
+        cached.replay_from(1);
+        assert_eq!(cached.size_hint(), (3, Some(3)));
+        assert_eq!(cached.next(), Some(2));
+        cached.clear_cache();
+        assert_eq!(cached.cache_len(), 1);
+        assert_eq!(cached.collect::<Vec<_>>(), vec![3,4]);
+
+        // Replaying works after the end too
+        let mut cached = CachedSeqIter::new(SeqIter::from_vec(vec![Box::new(vec![1].into_iter())]));
+        assert_eq!(cached.next(), Some(1));
+        assert_eq!(cached.next(), None);
+        cached.replay_from(0);
+        assert_eq!(cached.next(), Some(1));
+        assert_eq!(cached.next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cached_seq_replay_out_of_bounds() {
+        let mut cached = CachedSeqIter::new(SeqIter::from_vec(vec![Box::new(vec![1,2,3].into_iter())]));
+        cached.next();
+        cached.replay_from(2);
+    }
+
+    #[test]
+    fn test_lazy_seq() {
+        let created = Rc::new(std::cell::Cell::new(0));
+        let mut l_iter = LazySeqIter::new();
+        for v in [vec![1,2], vec![], vec![3]] {
+            let created = created.clone();
+            l_iter.add(move || {
+                created.set(created.get() + 1);
+                Box::new(v.into_iter()) as Box<dyn Iterator<Item = i32>>
+            });
         }
+        assert_eq!(l_iter.size_hint(), (0, None));
+        assert_eq!(l_iter.next(), Some(1));
+        assert_eq!(created.get(), 1);
+        assert_eq!(l_iter.pending_count(), 2);
+        assert_eq!(l_iter.next(), Some(2));
+        assert_eq!(created.get(), 1);
+        assert_eq!(l_iter.next(), Some(3));
+        assert_eq!(created.get(), 3);
+        assert_eq!(l_iter.next(), None);
+        assert_eq!(l_iter.size_hint(), (0, Some(0)));
 
-        let v1 = vec!(9,4,2);
-        let v2:Vec<i32> = vec!();
-        let v3 = vec!(999,22,3,1);
-        let v4 = [992,222,211,20,19,2,1];
+        // Fused: iterators added after the end are never created
+        let created = created.clone();
+        l_iter.add(move || {
+            created.set(created.get() + 1);
+            Box::new(vec![0].into_iter()) as Box<dyn Iterator<Item = i32>>
+        });
+        assert_eq!(l_iter.pending_count(), 0);
+        assert_eq!(l_iter.next(), None);
+    }
+
+    #[test]
+    fn test_cartesian_product() {
+        let mut product = CartesianProductIter::new(vec![
+            Box::new(vec![1,2].into_iter()) as Box<dyn Iterator<Item = i32>>,
+            Box::new(vec![3].into_iter()),
+            Box::new(vec![4,5,6].into_iter()),
+        ]);
+        assert_eq!(product.size_hint(), (6, Some(6)));
+        assert_eq!(product.next(), Some(vec![1,3,4]));
+        assert_eq!(product.size_hint(), (5, Some(5)));
+        assert_eq!(product.collect::<Vec<_>>(), vec![vec![1,3,5], vec![1,3,6], vec![2,3,4], vec![2,3,5], vec![2,3,6]]);
+
+        let mut product: CartesianProductIter<i32> = CartesianProductIter::new(Vec::new());
+        assert_eq!(product.next(), None);
+        let mut product = CartesianProductIter::new(vec![
+            Box::new(vec![1,2].into_iter()) as Box<dyn Iterator<Item = i32>>,
+            Box::new(std::iter::empty()),
+        ]);
+        assert_eq!(product.size_hint(), (0, Some(0)));
+        assert_eq!(product.next(), None);
+    }
+
+    #[test]
+    fn test_cartesian_product_overflow() {
+        // 256^9 combinations do not fit in a usize
+        let pools = (0..9).map(|_| Box::new(0..=255u8) as Box<dyn Iterator<Item = u8>>);
+        let mut product = CartesianProductIter::new(pools);
+        assert_eq!(product.size_hint(), (1, None));
+        assert_eq!(product.next(), Some(vec![0; 9]));
+        assert_eq!(product.next(), Some(vec![0, 0, 0, 0, 0, 0, 0, 0, 1]));
+        assert_eq!(product.size_hint(), (1, None));
+    }
+
+    #[test]
+    fn test_flatten_multi() {
+        let outer = vec![vec![1,2], vec![], vec![3]].into_iter().map(|x| Box::new(x.into_iter()) as Box<dyn Iterator<Item = i32>>);
+        let mut flat = FlattenMultiIter::new(Box::new(outer));
+        assert_eq!(flat.size_hint(), (0, None));
+        assert_eq!(flat.next(), Some(1));
+        assert_eq!(flat.next(), Some(2));
+        assert_eq!(flat.next(), Some(3));
+        assert_eq!(flat.size_hint(), (0, Some(0)));
+        assert_eq!(flat.next(), None);
+        assert_eq!(flat.next(), None);
+    }
+
+    #[test]
+    fn test_priority_queue() {
+        let mut pq_iter = PriorityQueueIter::new();
+        assert!(pq_iter.is_empty());
+        assert_eq!(pq_iter.next(), None);
+        pq_iter.push((1, 'a'));
+        pq_iter.push((3, 'b'));
+        pq_iter.push((2, 'c'));
+        assert_eq!(pq_iter.len(), 3);
+        assert_eq!(pq_iter.peek(), Some(&(3, 'b')));
+        assert_eq!(pq_iter.next(), Some((3, 'b')));
+        pq_iter.push((4, 'd'));
+        assert_eq!(pq_iter.size_hint(), (3, Some(3)));
+        assert_eq!(pq_iter.collect::<Vec<_>>(), vec![(4, 'd'), (2, 'c'), (1, 'a')]);
+    }
+
+    #[test]
+    fn test_partition_lazy() {
+        let (mut small, mut big) = partition_lazy(vec![5,1,7,2,8,3].into_iter(), |x| *x < 5);
+        assert_eq!(small.next(), Some(1));
+        assert_eq!(big.size_hint(), (1, Some(5)));
+        assert_eq!(small.next(), Some(2));
+        assert_eq!(big.next(), Some(5));
+        assert_eq!(big.next(), Some(7));
+        assert_eq!(small.next(), Some(3));
+        assert_eq!(small.next(), None);
+        assert_eq!(big.next(), Some(8));
+        assert_eq!(big.next(), None);
+
+        let (small, big) = partition_lazy(1..=4, |x| *x < 3);
+        drop(big);
+        assert_eq!(small.collect::<Vec<_>>(), vec![1,2]);
+    }
+
+    #[test]
+    fn test_scan_multi() {
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add(Box::new(vec![1,2,3,4].into_iter()));
+        m_iter.add(Box::new(vec![10,20].into_iter()));
+        // Take from each source while its running sum stays below 5
+        let scan = ScanMultiIter::new(m_iter, vec![0, 0], |sum, x| {
+            *sum += x;
+            if *sum < 5 { Some(x) } else { None }
+        });
+        assert_eq!(scan.collect::<Vec<_>>(), vec![1,2]);
+
+        let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+        m_iter.add(Box::new(vec![1,2,3].into_iter()));
+        m_iter.add(Box::new(vec![2].into_iter()));
+        let scan = ScanMultiIter::new(m_iter, vec![(), ()], |_, x| if x == 2 { None } else { Some(x) });
+        assert_eq!(scan.collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scan_multi_missing_state() {
+        let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+        m_iter.add(Box::new(vec![1].into_iter()));
+        ScanMultiIter::new(m_iter, Vec::<i32>::new(), |_, x| Some(x));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scan_multi_too_few_states() {
+        let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(vec![2].into_iter()));
+        // Even a source that is already exhausted needs a state
+        m_iter.add(Box::new(std::iter::empty()));
+        ScanMultiIter::new(m_iter, vec![0, 0], |_, x| Some(x));
+    }
+
+    #[test]
+    fn test_iter_utils_ext() {
+        let mut merged = (1..4).merge_sorted_with(vec![0, 2].into_iter());
+        merged.add(Box::new(vec![5].into_iter()));
+        assert_eq!(merged.collect::<Vec<_>>(), vec![0,1,2,2,3,5]);
+        let chained = (1..3).seq_chain(std::iter::empty()).seq_chain(0..1);
+        assert_eq!(chained.collect::<Vec<_>>(), vec![1,2,0]);
+        let alternated = (1..4).round_robin_with(std::iter::once(10));
+        assert_eq!(alternated.collect::<Vec<_>>(), vec![1,10,2,3]);
+        // Borrowed iterators work with every method
+        let (a, b) = ([1, 3], [2]);
+        let alternated = a.iter().round_robin_with(b.iter());
+        assert_eq!(alternated.copied().collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+    #[test]
+    fn test_seq_iter_macro() {
+        let s_iter: SeqIter<i32> = seq_iter![];
+        assert_eq!(s_iter.source_count(), 0);
+        let s_iter = seq_iter![
+            vec![1].into_iter(),
+            std::iter::empty(),
+            (2..4).map(|x| x * 10),
+        ];
+        assert_eq!(s_iter.source_count(), 3);
+        assert_eq!(s_iter.collect::<Vec<_>>(), vec![1,20,30]);
+    }
+
+    #[test]
+    fn test_ordered_iter_macros() {
+        let o_iter: OrderedIterator<i32> = ordered_iter![];
+        assert_eq!(o_iter.source_count(), 0);
+        let o_iter = ordered_iter![vec![1,5].into_iter(), 2..4, std::iter::empty(),];
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1,2,3,5]);
+        let o_iter = ordered_iter_desc![vec!['c', 'a'].into_iter(), std::iter::once('b')];
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec!['c', 'b', 'a']);
+    }
+
+    #[test]
+    fn test_multi_iter_macro() {
+        let m_iter = multi_iter!(|_: &[i32]| Some(0));
+        assert!(m_iter.is_empty());
+        let choose = |heads: &[i32]| heads.iter().enumerate().min_by_key(|x| x.1).map(|x| x.0);
+        let m_iter = multi_iter!(choose; vec![2,3].into_iter(), 1..2,);
+        assert_eq!(m_iter.len(), 2);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+    #[test]
+    fn test_seq_iter_builder() {
+        let exhausted = Rc::new(RefCell::new(Vec::new()));
+        let log = exhausted.clone();
+        let seq_iter = SeqIterBuilder::new()
+            .push(vec![1].into_iter())
+            .push(std::iter::empty())
+            .with_on_exhausted(move |i| log.borrow_mut().push(i))
+            .push(2..3)
+            .build();
+        assert_eq!(seq_iter.source_count(), 3);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1,2]);
+        assert_eq!(*exhausted.borrow(), vec![0,1,2]);
+
+        let seq_iter: SeqIter<i32> = SeqIterBuilder::new().build();
+        assert!(seq_iter.is_exhausted());
+    }
+
+    #[test]
+    fn test_ordered_builder() {
+        let o_iter = OrderedIterator::builder_max()
+            .add(vec![(1, 'a'), (0, 'a')].into_iter())
+            .add(vec![(1, 'b')].into_iter())
+            .stable(true)
+            .build();
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![(1, 'b'), (1, 'a'), (0, 'a')]);
+
+        let o_iter = OrderedIterator::builder_min()
+            .add(vec![(1, 'b'), (2, 'a')].into_iter())
+            .add(vec![(1, 'a'), (3, 'z')].into_iter())
+            .comparator(|x, y| x.0.cmp(&y.0))
+            .stable(true)
+            .build();
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![(1, 'b'), (1, 'a'), (2, 'a'), (3, 'z')]);
+
+        let mut o_iter = OrderedIterator::builder_min().add(vec![2, 1].into_iter()).check_sorted(true).build();
+        assert_eq!(o_iter.by_ref().collect::<Vec<_>>(), vec![2]);
+        assert!(o_iter.take_error().is_some());
+    }
+
+    #[test]
+    fn test_seq_from_vecs() {
+        let seq_iter: SeqIter<i32> = vec![vec![], vec![1], vec![2,3]].into();
+        assert_eq!(seq_iter.source_count(), 3);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![1,2,3]);
+    }
+
+    #[test]
+    fn test_ordered_from_vecs() {
+        let o_iter: OrderedIterator<i32> = vec![vec![1,4], vec![], vec![2,2,3]].into();
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![1,2,2,3,4]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_ordered_from_unsorted_vecs() {
+        let _: OrderedIterator<i32> = vec![vec![1,4], vec![3,2]].into();
+    }
+
+    #[test]
+    fn test_collect_vec() {
+        let seq_iter = SeqIter::from(vec![vec![1,2], vec![3]]);
+        assert_eq!(seq_iter.collect_vec(), vec![1,2,3]);
+
+        let mut m_iter = MultiIterator::min_by_key(|x: &i32| *x);
+        m_iter.add(Box::new(vec![2,3].into_iter()));
+        m_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(m_iter.collect_vec(), vec![1,2,3]);
+
+        let o_iter = OrderedIterator::from_sorted_vecs_desc(vec![vec![5,1], vec![4]]);
+        let v = o_iter.collect_vec();
+        assert_eq!(v, vec![5,4,1]);
+        assert!(v.capacity() >= 3);
+    }
+
+    #[test]
+    fn seq_extend() {
+        let mut seq_iter = SeqIter::from(vec![vec![1]]);
+        assert_eq!(seq_iter.next(), Some(1));
+        seq_iter.extend(vec![
+            Box::new(vec![2].into_iter()) as Box<dyn Iterator<Item = i32>>,
+            Box::new(3..5),
+        ]);
+        assert_eq!(seq_iter.source_count(), 3);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![2,3,4]);
+    }
+
+    #[test]
+    fn test_ordered_extend() {
+        let mut o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1,5]]);
+        assert_eq!(o_iter.next(), Some(1));
+        o_iter.extend(vec![
+            Box::new(vec![2,6].into_iter()) as Box<dyn Iterator<Item = i32>>,
+            Box::new(3..5),
+        ]);
+        assert_eq!(o_iter.source_count(), 3);
+        assert_eq!(o_iter.collect::<Vec<_>>(), vec![2,3,4,5,6]);
+    }
+
+    #[test]
+    fn seq_borrowed_sources() {
+        let data = [1,2,3,4];
+        let (left, right) = data.split_at(2);
+        let seq_iter = SeqIterBuilder::new()
+            .push(right.iter())
+            .push(left.iter())
+            .build();
+        let mut cached = CachedSeqIter::new(seq_iter);
+        assert_eq!(cached.by_ref().copied().collect::<Vec<_>>(), vec![3,4,1,2]);
+        cached.replay_from(3);
+        assert_eq!(cached.next(), Some(&2));
+        let chained = left.iter().seq_chain(right.iter().rev());
+        assert_eq!(chained.enumerate_global().map(|x| *x.2).collect::<Vec<_>>(), vec![1,2,4,3]);
+    }
+
+    #[test]
+    fn test_multi_borrowed_sources() {
+        let data = [5,1,4,2];
+        let picks = std::cell::Cell::new(0);
+        let mut m_iter = MultiIterator::new(|heads: &[&i32]| {
+            picks.set(picks.get() + 1);
+            heads.iter().enumerate().min_by_key(|x| *x.1).map(|x| x.0)
+        });
+        m_iter.add(Box::new(data[..2].iter()));
+        m_iter.add(Box::new(data[2..].iter()));
+        let tagged = m_iter.enumerate_sources().collect::<Vec<_>>();
+        assert_eq!(tagged, vec![(1,&4), (1,&2), (0,&5), (0,&1)]);
+        assert_eq!(picks.get(), 4);
+    }
+
+    #[test]
+    fn test_ordered_borrowed_sources() {
+        let a = [1,4,4,7];
+        let b = [2,4,8];
+        let merged = a.iter().merge_sorted_with(b.iter()).dedup().copied().collect::<Vec<_>>();
+        assert_eq!(merged, vec![1,2,4,7,8]);
+
+        let names = [String::from("b"), String::from("a")];
+        let o_iter = OrderedIterator::builder_max().add(names.iter()).add(std::iter::once(&names[1])).build();
+        assert_eq!(o_iter.map(|x| x.as_str()).collect::<String>(), "baa");
+
+        let mut union = UnionSortedIter::new();
+        union.add(Box::new(a.iter()));
+        union.add(Box::new(b.iter()));
+        assert_eq!(union.count(), 5);
+    }
+
+    #[test]
+    fn test_clonable_seq() {
+        let data = [1,2,3];
+        let mut c_iter = ClonableSeqIter::new();
+        c_iter.add(Box::new(data.iter()));
+        c_iter.add(Box::new(std::iter::empty()));
+        c_iter.add(Box::new(data.iter().rev()));
+        assert_eq!(c_iter.by_ref().take(4).count(), 4);
+        let copy = c_iter.clone();
+        assert_eq!(copy.source_count(), 1);
+        assert_eq!(copy.size_hint(), (2, Some(2)));
+        assert_eq!(copy.copied().collect::<Vec<_>>(), vec![2,1]);
+        assert_eq!(c_iter.by_ref().copied().collect::<Vec<_>>(), vec![2,1]);
+
+        // Fused, clones included
+        c_iter.add(Box::new(data.iter()));
+        assert_eq!(c_iter.size_hint(), (0, Some(0)));
+        assert_eq!(c_iter.clone().next(), None);
+        assert_eq!(c_iter.next(), None);
+    }
+
+    #[test]
+    fn seq_debug() {
+        let mut seq_iter = SeqIter::from(vec![vec![1], vec![], vec![2]]);
+        assert_eq!(format!("{:?}", seq_iter), "SeqIter { ptr: 0, sources: 3 }");
+        seq_iter.next();
+        seq_iter.next();
+        assert_eq!(format!("{:?}", seq_iter), "SeqIter { ptr: 2, sources: 3 }");
+    }
+
+    #[test]
+    fn seq_take_n_from_each() {
+        let mut seq_iter = SeqIter::from(vec![vec![1,2,3], vec![], vec![4,5]]);
+        assert_eq!(seq_iter.next(), Some(1));
+        let mut seq_iter = seq_iter.take_n_from_each(1);
+        seq_iter.add(Box::new(vec![6,7].into_iter()));
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![2,4,6,7]);
+
+        let seq_iter = SeqIter::from(vec![vec![1,2]]).take_n_from_each(0);
+        assert_eq!(seq_iter.count(), 0);
+    }
+
+    #[test]
+    fn seq_skip_n_from_each() {
+        let seq_iter = SeqIter::from(vec![vec![1,2,3], vec![4], vec![5,6,7]]).skip_n_from_each(2);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![3,7]);
+
+        let seq_iter = SeqIter::from(vec![vec![1,2,3], vec![4,5,6]]).skip_n_from_each(1).take_n_from_each(1);
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![2,5]);
+    }
 
+    #[test]
+    fn test_multi_add_with_filter() {
+        let limit = 3;
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(0));
+        m_iter.add_with_filter(Box::new(vec![1,5,2,6].into_iter()), Box::new(|x| *x < limit));
+        m_iter.add_with_filter(Box::new(vec![1,2].into_iter()), Box::new(|_| false));
+        m_iter.add(Box::new(vec![9].into_iter()));
+        // The source without matching items is dropped when added
+        assert_eq!(m_iter.len(), 2);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![1,2,9]);
+    }
+
+    #[test]
+    fn test_multi_add_mapped() {
+        let mut m_iter = MultiIterator::max_by_key(|x: &String| x.len());
+        m_iter.add_mapped(Box::new(vec![100, 1].into_iter()), |x: u32| x.to_string());
+        m_iter.add_mapped(Box::new(vec!['a'].into_iter()), String::from);
+        m_iter.add(Box::new(vec![String::from("xy")].into_iter()));
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec!["100", "xy", "1", "a"]);
+    }
+
+    #[test]
+    fn test_ordered_batch_next() {
         let mut o_iter = OrderedIterator::new_max();
-        o_iter.add(Box::new(v1.into_iter()));
-        o_iter.add(Box::new(v2.into_iter()));
-        o_iter.add(Box::new(v3.into_iter()));
-        o_iter.add(Box::new(v4.into_iter()));
-        for i in o_iter {
-            println!("{i}");
+        o_iter.add(Box::new(vec![9,5,1].into_iter()));
+        o_iter.add(Box::new(vec![8,2].into_iter()));
+        assert!(o_iter.batch_next(0).is_empty());
+        assert_eq!(o_iter.batch_next(3), vec![9,8,5]);
+        o_iter.add(Box::new(vec![7].into_iter()));
+        assert_eq!(o_iter.batch_next(10), vec![7,2,1]);
+        assert!(o_iter.batch_next(10).is_empty());
+    }
+
+    #[test]
+    fn seq_into_with_stats() {
+        let mut seq_iter = SeqIter::from(vec![vec![1], vec![2,3,4]]);
+        assert_eq!(seq_iter.next(), Some(1));
+        let (mut seq_iter, stats) = seq_iter.into_with_stats();
+        assert_eq!(stats.items_per_source(), vec![0,0]);
+        assert_eq!(seq_iter.next(), Some(2));
+        assert_eq!(stats.items_per_source(), vec![0,1]);
+        let reader = stats.clone();
+        assert_eq!(std::thread::spawn(move || reader.total()).join().unwrap(), 1);
+        assert_eq!(seq_iter.count(), 2);
+        assert_eq!(stats.items_per_source(), vec![0,3]);
+        assert_eq!(stats.total(), 3);
+    }
+
+    #[test]
+    fn test_multi_into_with_stats() {
+        let mut m_iter = MultiIterator::new(|heads: &[i32]| Some(heads.len() - 1));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(std::iter::empty()));
+        m_iter.add(Box::new(vec![3,4,5].into_iter()));
+        let (mut m_iter, stats) = m_iter.into_with_stats();
+        assert_eq!(stats.chosen_counts(), vec![0,0,0]);
+        assert_eq!(m_iter.next(), Some(3));
+        assert_eq!(stats.chosen_counts(), vec![0,0,1]);
+        assert_eq!(m_iter.collect::<Vec<_>>(), vec![4,5,1,2]);
+        assert_eq!(stats.chosen_counts(), vec![2,0,3]);
+    }
+
+    #[test]
+    fn ordered_with_progress() {
+        let reports = RefCell::new(Vec::new());
+        let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1,4,7], vec![2,5], vec![3,6]]);
+        let mut p_iter = o_iter.with_progress(3, |n| reports.borrow_mut().push(n));
+        assert_eq!(p_iter.by_ref().take(2).collect::<Vec<_>>(), vec![1,2]);
+        assert!(reports.borrow().is_empty());
+        assert_eq!(p_iter.collect::<Vec<_>>(), vec![3,4,5,6,7]);
+        assert_eq!(reports.into_inner(), vec![3,6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ordered_with_progress_zero_interval() {
+        let o_iter = OrderedIterator::from_sorted_vecs(vec![vec![1]]);
+        let _ = o_iter.with_progress(0, |_| {});
+    }
+
+    #[test]
+    fn seq_many_empty_sources() {
+        let mut seq_iter = SeqIter::new();
+        for _ in 0..100_000 {
+            seq_iter.add(Box::new(std::iter::empty()));
+        }
+        seq_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.next(), None);
+    }
+
+    #[test]
+    fn test_multi_invalid_index() {
+        let bad = Rc::new(RefCell::new(vec![]));
+        let log = bad.clone();
+        let mut m_iter = MultiIterator::new(|heads: &[i32]| Some(heads.len()))
+            .with_on_invalid_index(move |i| log.borrow_mut().push(i));
+        m_iter.add(Box::new(vec![1,2].into_iter()));
+        m_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(m_iter.next(), None);
+        assert_eq!(m_iter.next(), None);
+        assert_eq!(*bad.borrow(), vec![2]);
+
+        // Without a callback it just ends
+        let mut m_iter = MultiIterator::new(|_: &[i32]| Some(usize::MAX));
+        m_iter.add(Box::new(vec![1].into_iter()));
+        assert_eq!(m_iter.next(), None);
+    }
+
+    #[test]
+    fn seq_add_after_start() {
+        let mut seq_iter = SeqIter::new();
+        seq_iter.add(Box::new(vec![1,2].into_iter()));
+        seq_iter.add(Box::new(vec![3].into_iter()));
+        assert_eq!(seq_iter.next(), Some(1));
+        assert_eq!(seq_iter.next(), Some(2));
+        assert_eq!(seq_iter.next(), Some(3));
+        assert_eq!(seq_iter.current_index(), 1);
+        seq_iter.add(Box::new(vec![4].into_iter()));
+        assert_eq!(seq_iter.next(), Some(4));
+        assert_eq!(seq_iter.next(), None);
+        // Fused: iterators added after the end are never consumed
+        seq_iter.add(Box::new(vec![5,6].into_iter()));
+        assert_eq!(seq_iter.collect::<Vec<_>>(), vec![]);
+    }
+
+    #[test]
+    fn test_ordered_equal_items_deterministic() {
+        let run = || {
+            let mut o_iter = OrderedIterator::new_by_key(|x: &(u32, u32)| x.0);
+            o_iter.add(Box::new((0..100).map(|i| (i / 50, i))));
+            o_iter.add(Box::new((100..200).map(|i| (i / 150, i))));
+            o_iter.collect::<Vec<_>>()
+        };
+        let first = run();
+        let mut expected: Vec<_> = (0..50).map(|i| (0, i)).collect();
+        expected.extend((100..150).map(|i| (0, i)));
+        expected.extend((50..100).map(|i| (1, i)));
+        expected.extend((150..200).map(|i| (1, i)));
+        assert_eq!(first, expected);
+        assert_eq!(run(), first);
+    }
+
+    #[test]
+    fn test_multi_add_empty() {
+        let mut m_iter = MultiIterator::new(|heads: &[i32]| {
+            assert_eq!(heads.len(), 1);
+            Some(0)
+        });
+        m_iter.add(Box::new(std::iter::empty()));
+        assert!(m_iter.is_empty());
+        assert_eq!(m_iter.len(), 0);
+        m_iter.add(Box::new(vec![1].into_iter()));
+        m_iter.add(Box::new(std::iter::empty()));
+        assert_eq!(m_iter.len(), 1);
+        assert_eq!(m_iter.next(), Some(1));
+        assert_eq!(m_iter.next(), None);
+        m_iter.add(Box::new(std::iter::empty()));
+        assert_eq!(m_iter.next(), None);
+    }
+
+    #[test]
+    fn test_multi_many_sources() {
+        // Exhausting sources from the front would shift all others with
+        // Vec::remove, making this quadratic
+        let n = 100_000;
+        let mut m_iter = MultiIterator::new(|_: &[usize]| Some(0));
+        for i in 0..n {
+            m_iter.add(Box::new(std::iter::once(i)));
         }
+        let (m_iter, stats) = m_iter.into_with_stats();
+        let mut result: Vec<_> = m_iter.collect();
+        assert_eq!(result.len(), n);
+        assert!(stats.chosen_counts().iter().all(|x| *x == 1));
+        result.sort();
+        assert!(result.iter().copied().eq(0..n));
     }
 }